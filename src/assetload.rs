@@ -2,17 +2,20 @@ use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 use bevy_kira_audio::AudioSource;
 
-use crate::AppState;
+use crate::{
+    autotile::TransitionTable, crafting::RecipeBook, item_database::ItemDatabase, AppState,
+};
 pub struct AssetLoadPlugin;
 
 impl Plugin for AssetLoadPlugin {
     fn build(&self, app: &mut App) {
         app.add_loading_state(
             LoadingState::new(AppState::AssetLoad)
-                .continue_to_state(AppState::GameLoad)
+                .continue_to_state(AppState::MainMenu)
                 .with_collection::<SpriteAssets>()
                 .with_collection::<SoundAssets>()
-                .with_collection::<FontAssets>(),
+                .with_collection::<FontAssets>()
+                .with_collection::<DataAssets>(),
         )
         .add_state(AppState::AssetLoad);
     }
@@ -44,6 +47,17 @@ pub struct SpriteAssets {
 pub struct SoundAssets {
     #[asset(path = "sounds/pickup.wav")]
     pub item_pickup: Handle<AudioSource>,
+    #[asset(path = "sounds/craft.wav")]
+    pub craft: Handle<AudioSource>,
+    #[asset(path = "sounds/monsterhurt.wav")]
+    pub monster_hurt: Handle<AudioSource>,
+    #[asset(path = "sounds/playerhurt.wav")]
+    pub player_hurt: Handle<AudioSource>,
+    #[asset(path = "sounds/death.wav")]
+    pub death: Handle<AudioSource>,
+    // stand in until tiles have sounds of their own
+    #[asset(path = "sounds/test.wav")]
+    pub tile_hit: Handle<AudioSource>,
 }
 
 #[derive(AssetCollection, Resource)]
@@ -53,3 +67,13 @@ pub struct FontAssets {
     #[asset(path = "fonts/monogram.ttf")]
     pub monogram: Handle<Font>,
 }
+
+#[derive(AssetCollection, Resource)]
+pub struct DataAssets {
+    #[asset(path = "data/items.ron")]
+    pub items: Handle<ItemDatabase>,
+    #[asset(path = "data/recipes.ron")]
+    pub recipes: Handle<RecipeBook>,
+    #[asset(path = "data/autotile.ron")]
+    pub autotile: Handle<TransitionTable>,
+}
@@ -0,0 +1,271 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    utils::{BoxedFuture, HashMap},
+};
+use bevy_ecs_tilemap::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    assetload::DataAssets,
+    map_generation::{LoadedChunks, TileChanged, WorldEdits, WorldGenerator},
+    tile_type::{Sheet, TileType},
+    AppState, SpriteAssets,
+};
+
+/*
+    Edges between terrain
+
+    A terrain that blends into its neighbours has a run of 16 variants in tiles.png,
+    one for every combination of which sides join up with the same terrain, so the
+    missing sides get an edge and two missing sides next to each other make a corner.
+    Moving terrain like water has a few frames of each variant in a row which
+    bevy_ecs_tilemap cycles through on the gpu.
+
+    Where each terrain's run starts is in assets/data/autotile.ron and is checked
+    against the size of tiles.png once it's loaded. Terrain that isn't in there is
+    drawn with its plain tile.
+
+    A tilemap only draws from one texture, so tiles2.png is stacked under tiles.png
+    into one sheet that every chunk uses.
+*/
+
+const SHEET_TILE_SIZE: f32 = 16.; // pixels
+const VARIANTS: u32 = 16;
+
+// Bits of a neighbour mask, set when the tile on that side is the same terrain
+const NORTH: u32 = 1;
+const EAST: u32 = 2;
+const SOUTH: u32 = 4;
+const WEST: u32 = 8;
+const SIDES: [(IVec2, u32); 4] = [
+    (IVec2::new(0, 1), NORTH),
+    (IVec2::new(1, 0), EAST),
+    (IVec2::new(0, -1), SOUTH),
+    (IVec2::new(-1, 0), WEST),
+];
+
+pub struct AutotilePlugin;
+
+impl Plugin for AutotilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<TransitionTable>()
+            .init_asset_loader::<TransitionTableLoader>()
+            .add_system_set(SystemSet::on_exit(AppState::AssetLoad).with_system(insert_tile_sheet))
+            .add_system_set(SystemSet::on_update(AppState::InGame).with_system(retile_changed));
+    }
+}
+
+/// Where a terrain's variants start in tiles.png and how they animate
+#[derive(Deserialize, Clone, Copy)]
+pub struct Transitions {
+    tile: TileType,
+    first: u32,
+    frames: u32,
+    #[serde(default)]
+    speed: f32,
+}
+
+/// Transitions as they are written in assets/data/autotile.ron
+#[derive(TypeUuid)]
+#[uuid = "a3c95e1d-7f24-4b0e-8d6a-52e1f0b7c9d4"]
+pub struct TransitionTable(pub Vec<Transitions>);
+
+/// Both tile sheets in one texture, and the terrains that blend into their neighbours
+#[derive(Resource)]
+pub struct TileSheet {
+    pub texture: Handle<Image>,
+    columns: u32,
+    /// Columns of each sheet and the row it starts on in the texture, by Sheet
+    sheets: [(u32, u32); 2],
+    transitions: HashMap<TileType, Transitions>,
+}
+
+impl TileSheet {
+    fn index(&self, sheet: Sheet, index: u32) -> u32 {
+        let (columns, first_row) = self.sheets[sheet as usize];
+        (first_row + index / columns) * self.columns + index % columns
+    }
+
+    /// The texture a tile is drawn with and its animation if it has one
+    pub fn tile_look(&self, tile: TileType, mask: u32) -> (TileTextureIndex, Option<AnimatedTile>) {
+        let transitions = match self.transitions.get(&tile) {
+            Some(transitions) => transitions,
+            None => {
+                let props = tile.props();
+                return (
+                    TileTextureIndex(self.index(props.sheet, props.texture)),
+                    None,
+                );
+            }
+        };
+        let start = self.index(Sheet::Tiles, transitions.first + mask * transitions.frames);
+        let animation = (transitions.frames > 1).then_some(AnimatedTile {
+            start,
+            end: start + transitions.frames,
+            speed: transitions.speed,
+        });
+        (TileTextureIndex(start), animation)
+    }
+}
+
+// Every tile has to be on its sheet, one that isn't is only warned about since it
+// still draws, just with the wrong art. A terrain whose edges run off of the end of
+// tiles.png is drawn plain rather than with whatever else is at those indices
+fn insert_tile_sheet(
+    mut commands: Commands,
+    data: Res<DataAssets>,
+    tables: Res<Assets<TransitionTable>>,
+    sprites: Res<SpriteAssets>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let table = tables
+        .get(&data.autotile)
+        .expect("The autotile table should be loaded by now");
+    let sources = [&sprites.tiles1, &sprites.tiles2].map(|handle| {
+        images
+            .get(handle)
+            .expect("The tile sheets should be loaded by now")
+    });
+    // in tiles
+    let sizes = sources.map(|image| (image.size() / SHEET_TILE_SIZE).as_uvec2());
+    let columns = sizes[0].x.max(sizes[1].x);
+    let sheets = [(sizes[0].x, 0), (sizes[1].x, sizes[0].y)];
+
+    // pngs are always loaded as 8 bit rgba
+    let pixel_size = 4;
+    let tile_px = SHEET_TILE_SIZE as usize;
+    let width = columns as usize * tile_px;
+    let height = (sizes[0].y + sizes[1].y) as usize * tile_px;
+    let mut pixels = vec![0; width * height * pixel_size];
+    for (source, (size, (_, first_row))) in sources.iter().zip(sizes.iter().zip(sheets)) {
+        let source_width = source.texture_descriptor.size.width as usize;
+        let row_len = size.x as usize * tile_px * pixel_size;
+        for y in 0..size.y as usize * tile_px {
+            let from = y * source_width * pixel_size;
+            let to = ((first_row as usize * tile_px + y) * width) * pixel_size;
+            pixels[to..to + row_len].copy_from_slice(&source.data[from..from + row_len]);
+        }
+    }
+
+    for tile in TileType::all() {
+        let props = tile.props();
+        let size = sizes[props.sheet as usize];
+        if props.texture >= size.x * size.y {
+            warn!(
+                "{:?} is drawn with tile {} of {} but it only has {}",
+                tile,
+                props.texture,
+                props.sheet.file(),
+                size.x * size.y
+            );
+        }
+    }
+
+    let mut transitions = HashMap::default();
+    for run in table.0.iter() {
+        let end = run.first + VARIANTS * run.frames;
+        if end > sizes[0].x * sizes[0].y {
+            warn!(
+                "The edges of {:?} need tiles up to {} but tiles.png only has {}",
+                run.tile,
+                end,
+                sizes[0].x * sizes[0].y
+            );
+            continue;
+        }
+        transitions.insert(run.tile, *run);
+    }
+
+    let mut texture = Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        pixels,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    // the tilemap copies tiles out of it into a texture array
+    texture.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+    commands.insert_resource(TileSheet {
+        texture: images.add(texture),
+        columns,
+        sheets,
+        transitions,
+    });
+}
+
+/// Which sides of a tile join up with the same terrain, `neighbour` gives the type
+/// of the tile at an offset from it
+pub fn neighbour_mask(tile: TileType, neighbour: impl Fn(IVec2) -> TileType) -> u32 {
+    SIDES
+        .iter()
+        .filter(|(offset, _)| neighbour(*offset) == tile)
+        .fold(0, |mask, (_, side)| mask | side)
+}
+
+// A changed tile changes the edges of the tiles around it too
+#[allow(clippy::too_many_arguments)]
+fn retile_changed(
+    mut commands: Commands,
+    mut ev_tile_changed: EventReader<TileChanged>,
+    loaded: Res<LoadedChunks>,
+    storages: Query<&TileStorage>,
+    types: Query<&TileType>,
+    mut textures: Query<&mut TileTextureIndex>,
+    generator: Res<WorldGenerator>,
+    edits: Res<WorldEdits>,
+    sheet: Res<TileSheet>,
+) {
+    for ev in ev_tile_changed.iter() {
+        let around = SIDES.iter().map(|(offset, _)| ev.tile + *offset);
+        for target in std::iter::once(ev.tile).chain(around) {
+            let entity = match loaded.tile_entity(&storages, target) {
+                Some(entity) => entity,
+                None => continue,
+            };
+            // neighbours in chunks that aren't spawned are what the generator makes
+            let type_at = |tile: IVec2| {
+                loaded
+                    .tile_type(&storages, &types, tile)
+                    .unwrap_or_else(|| edits.tile_at(&generator, tile))
+            };
+            let tile_type = type_at(target);
+            let mask = neighbour_mask(tile_type, |offset| type_at(target + offset));
+            let (texture, animation) = sheet.tile_look(tile_type, mask);
+
+            if let Ok(mut current) = textures.get_mut(entity) {
+                *current = texture;
+            }
+            match animation {
+                Some(animation) => commands.entity(entity).insert(animation),
+                None => commands.entity(entity).remove::<AnimatedTile>(),
+            };
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TransitionTableLoader;
+
+impl AssetLoader for TransitionTableLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let defs = ron::de::from_bytes::<Vec<Transitions>>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(TransitionTable(defs)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["autotile.ron"]
+    }
+}
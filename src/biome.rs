@@ -0,0 +1,94 @@
+use crate::{decoration::Decoration, tile_type::TileType};
+
+/// The kind of land a tile belongs to, picked from the elevation, moisture and
+/// temperature noise at that tile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Biome {
+    Ocean,
+    Beach,
+    Plains,
+    Forest,
+    Desert,
+    Tundra,
+    Swamp,
+    Mountains,
+}
+
+/// The tiles a biome is made of, patches are laid over the ground wherever the
+/// detail noise goes above their threshold so they come out in clumps.
+/// Decorations are rolled once per placement cell, each with its own chance
+pub struct Palette {
+    pub ground: TileType,
+    pub patches: &'static [(TileType, f32)],
+    pub decorations: &'static [(Decoration, f32)],
+}
+
+const SEA_LEVEL: f32 = 0.3;
+const BEACH_LEVEL: f32 = 0.34;
+const MOUNTAIN_LEVEL: f32 = 0.78;
+
+impl Biome {
+    /// All inputs are in 0..1
+    pub fn pick(elevation: f32, moisture: f32, temperature: f32) -> Self {
+        if elevation < SEA_LEVEL {
+            Biome::Ocean
+        } else if elevation < BEACH_LEVEL {
+            // cold coasts freeze over instead of turning to sand
+            match temperature < 0.3 {
+                true => Biome::Tundra,
+                false => Biome::Beach,
+            }
+        } else if elevation > MOUNTAIN_LEVEL {
+            Biome::Mountains
+        } else if temperature < 0.3 {
+            Biome::Tundra
+        } else if temperature > 0.65 && moisture < 0.4 {
+            Biome::Desert
+        } else if moisture > 0.68 {
+            Biome::Swamp
+        } else if moisture > 0.48 {
+            Biome::Forest
+        } else {
+            Biome::Plains
+        }
+    }
+
+    pub fn palette(&self) -> Palette {
+        use Decoration::*;
+        use TileType::*;
+        type Rules = (
+            TileType,
+            &'static [(TileType, f32)],
+            &'static [(Decoration, f32)],
+        );
+        let (ground, patches, decorations): Rules = match self {
+            Biome::Ocean => (Water, &[], &[]),
+            Biome::Beach => (Sand, &[], &[]),
+            Biome::Plains => (
+                Grass,
+                &[(DarkGrass, 0.8)],
+                &[(Flower, 0.2), (Bush, 0.05), (Tree, 0.05)],
+            ),
+            Biome::Forest => (
+                DarkGrass,
+                &[(Grass, 0.75)],
+                &[(Tree, 0.6), (Bush, 0.15), (Flower, 0.05)],
+            ),
+            Biome::Desert => (Sand, &[(Stone, 0.82)], &[(Cactus, 0.15), (IronOre, 0.03)]),
+            Biome::Tundra => (Snow, &[(Ice, 0.72)], &[(Tree, 0.08), (IronOre, 0.03)]),
+            // pools of standing water with mud around them
+            Biome::Swamp => (
+                Mud,
+                &[(Water, 0.68), (DarkGrass, 0.6)],
+                &[(Bush, 0.2), (Tree, 0.1)],
+            ),
+            // paths of dirt running through the rock
+            Biome::Mountains => (Stone, &[(Dirt, 0.7)], &[(IronOre, 0.25)]),
+        };
+        Palette {
+            ground,
+            patches,
+            decorations,
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use super::player::Player;
-use crate::AppState;
+use crate::{
+    controls::{Action, Actions},
+    AppState, SessionEntity,
+};
 use bevy::{input::mouse::MouseWheel, prelude::*};
 
 const Z_CAM: f32 = 100.;
@@ -17,8 +20,9 @@ impl Plugin for CameraPlugin {
     }
 }
 
+/// While locked the mouse wheel is left for the hotbar instead of zooming
 #[derive(Component)]
-struct CamScrollLock(bool);
+pub struct CamScrollLock(pub bool);
 
 fn load_camera(mut commands: Commands) {
     let _camera_entity = commands
@@ -32,6 +36,7 @@ fn load_camera(mut commands: Commands) {
                 ..default()
             },
             CamScrollLock(true),
+            SessionEntity,
         ))
         .id();
 }
@@ -49,15 +54,14 @@ fn camera_follow_player(
 }
 
 //TODO: zoom_scroll_speed could become a component probably?
-//TODO: could try to abstract input from this function
 fn zoom_camera(
     mut camera_query: Query<(&mut Transform, &Camera2d, &mut CamScrollLock), Without<Player>>,
     mut scroll_wheel: EventReader<MouseWheel>,
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<Actions>,
 ) {
     let (mut cam, _, mut cam_lock) = camera_query.single_mut();
 
-    if keyboard_input.pressed(KeyCode::L) {
+    if actions.just_pressed(Action::ToggleZoomLock) {
         cam_lock.0 = !cam_lock.0;
     }
     if cam_lock.0 {
@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+
+/*
+    Cellular automaton caves for the levels below the surface
+
+    Tiles start out as walls at random and are smoothed a few times, a tile becomes
+    a wall when most of its neighbours are, which clumps the walls into caverns joined
+    by tunnels. After n steps a tile only depends on the starting fill within n tiles
+    of it, so running over a chunk plus that much border gives exactly what the
+    neighbouring chunks expect to see along their edges.
+*/
+
+const STEPS: i32 = 4;
+const FILL_CHANCE: f32 = 0.45;
+
+/// Which tiles of the `size` x `size` square starting at `origin` are walls, row by
+/// row. `random` gives the same number in 0..1 for a tile every time
+pub fn carve(origin: IVec2, size: i32, random: impl Fn(IVec2) -> f32) -> Vec<bool> {
+    let full = size + STEPS * 2;
+    let start = origin - IVec2::splat(STEPS);
+    let index = |x: i32, y: i32| (y * full + x) as usize;
+
+    let mut walls: Vec<bool> = (0..full * full)
+        .map(|i| random(start + IVec2::new(i % full, i / full)) < FILL_CHANCE)
+        .collect();
+
+    for step in 1..=STEPS {
+        // the border that is still correct shrinks by a tile every step
+        let mut next = walls.clone();
+        for y in step..full - step {
+            for x in step..full - step {
+                let mut neighbours = 0;
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        if (dx, dy) != (0, 0) && walls[index(x + dx, y + dy)] {
+                            neighbours += 1;
+                        }
+                    }
+                }
+                next[index(x, y)] = neighbours >= 5 || (neighbours == 4 && walls[index(x, y)]);
+            }
+        }
+        walls = next;
+    }
+
+    (0..size * size)
+        .map(|i| walls[index(i % size + STEPS, i / size + STEPS)])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Any fixed noise will do, it only has to give a tile the same number every time
+    fn random(tile: IVec2) -> f32 {
+        let mut x = (tile.x as u32 as u64) << 32 | tile.y as u32 as u64;
+        x = (x ^ (x >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94D049BB133111EB);
+        (x >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    #[test]
+    fn chunks_match_one_carve_over_the_same_area() {
+        let size = 8;
+        let origin = IVec2::new(-size, -3);
+        let whole = carve(origin, size * 2, random);
+
+        for chunk in [IVec2::ZERO, IVec2::X, IVec2::Y, IVec2::ONE] {
+            let part = carve(origin + chunk * size, size, random);
+            for y in 0..size {
+                for x in 0..size {
+                    let in_whole = (chunk.y * size + y) * size * 2 + chunk.x * size + x;
+                    assert_eq!(
+                        part[(y * size + x) as usize],
+                        whole[in_whole as usize],
+                        "chunk {} differs at {}, {}",
+                        chunk,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+    }
+}
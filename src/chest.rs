@@ -0,0 +1,291 @@
+use bevy::{prelude::*, ui::widget::ImageMode};
+
+use crate::{
+    controls::{Action, Actions},
+    furniture::{in_reach, spawn_furniture},
+    inventory::{Inventory, InventoryUpdate, MenuWindow},
+    item_database::ItemDatabase,
+    player::{Direction, Player},
+    AppState, FontAssets, SessionEntity, SpriteAssets,
+};
+
+const Z_UI: f32 = 80.;
+const CHEST_LINES: usize = 18;
+
+pub struct ChestPlugin;
+
+impl Plugin for ChestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OpenChest>()
+            .init_resource::<ChestCursor>()
+            .add_system_set(SystemSet::on_enter(AppState::GameLoad).with_system(chest_ui_startup))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(open_chest)
+                    .with_system(chest_ui_input.after(open_chest))
+                    .with_system(ui_chest_update.after(chest_ui_input)),
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct Chest;
+
+/// The chest whose contents are on screen
+#[derive(Resource, Default)]
+pub struct OpenChest(pub Option<Entity>);
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum Side {
+    #[default]
+    Player,
+    Chest,
+}
+
+// A cursor for each side of the window, moving left and right picks which side is being moved
+#[derive(Resource, Default)]
+struct ChestCursor {
+    side: Side,
+    player: usize,
+    chest: usize,
+}
+
+#[derive(Component)]
+struct ChestUINode;
+
+#[derive(Component)]
+struct ChestSlotText {
+    side: Side,
+    line: usize,
+}
+
+/// A chest is a piece of furniture that also holds an inventory
+pub fn spawn_chest(
+    commands: &mut Commands,
+    sprites: &SpriteAssets,
+    item_db: &ItemDatabase,
+    pos: Vec2,
+    inventory: Inventory,
+) -> Entity {
+    let chest_id = item_db.item("chest", 1).id;
+    let chest = spawn_furniture(commands, sprites, item_db, chest_id, pos);
+    commands.entity(chest).insert((Chest, inventory));
+    chest
+}
+
+// Interacting opens the chest in front of the player and closes whichever one is open
+fn open_chest(
+    actions: Res<Actions>,
+    player_q: Query<(&Transform, &Direction), With<Player>>,
+    chests: Query<(Entity, &Transform), With<Chest>>,
+    menus: Query<&Visibility, (With<MenuWindow>, Without<ChestUINode>)>,
+    mut open: ResMut<OpenChest>,
+    mut cursor: ResMut<ChestCursor>,
+) {
+    // the chest could have been taken away while it was open
+    if let Some(chest) = open.0 {
+        if !chests.contains(chest) {
+            open.0 = None;
+        }
+    }
+
+    if !actions.just_pressed(Action::Interact) {
+        return;
+    }
+    if open.0.is_some() {
+        open.0 = None;
+        return;
+    }
+    if menus.iter().any(|menu| menu.is_visible) {
+        return;
+    }
+
+    let (transform, direction) = player_q.single();
+    if let Some((chest, _)) = chests
+        .iter()
+        .find(|(_, chest)| in_reach(transform, direction, chest))
+    {
+        open.0 = Some(chest);
+        *cursor = ChestCursor::default();
+    }
+}
+
+fn chest_ui_input(
+    actions: Res<Actions>,
+    open: Res<OpenChest>,
+    mut cursor: ResMut<ChestCursor>,
+    mut inventories: Query<&mut Inventory>,
+    player_q: Query<Entity, With<Player>>,
+    item_db: Res<ItemDatabase>,
+    mut ev_inventory_update: EventWriter<InventoryUpdate>,
+) {
+    let chest = match open.0 {
+        Some(chest) => chest,
+        None => return,
+    };
+    let [mut player_inv, mut chest_inv] = match inventories.get_many_mut([player_q.single(), chest])
+    {
+        Ok(inventories) => inventories,
+        Err(_) => return,
+    };
+
+    if actions.just_pressed(Action::MoveLeft) {
+        cursor.side = Side::Player;
+    }
+    if actions.just_pressed(Action::MoveRight) {
+        cursor.side = Side::Chest;
+    }
+
+    let side = cursor.side;
+    let (from, to, selected) = match side {
+        Side::Player => (&mut player_inv, &mut chest_inv, &mut cursor.player),
+        Side::Chest => (&mut chest_inv, &mut player_inv, &mut cursor.chest),
+    };
+
+    let last = from.items.len().saturating_sub(1);
+    if actions.just_pressed(Action::MoveUp) {
+        *selected = selected.saturating_sub(1);
+    }
+    if actions.just_pressed(Action::MoveDown) {
+        *selected += 1;
+    }
+    *selected = (*selected).min(last);
+
+    // confirming moves one of the selected item across, or the whole stack while
+    // the stack modifier is held, same as dropping
+    if !actions.just_pressed(Action::Confirm) {
+        return;
+    }
+    let amt = if actions.pressed(Action::WholeStack) {
+        u32::MAX
+    } else {
+        1
+    };
+
+    // only as much as the other side has room for is taken
+    let room = match from.items.get(*selected) {
+        Some(item) => to.room_for(item.id, &item_db),
+        None => return,
+    };
+    if room == 0 {
+        return;
+    }
+    if let Some(moved) = from.take_from_slot(*selected, amt.min(room)) {
+        to.try_add(moved, &item_db);
+        ev_inventory_update.send(InventoryUpdate);
+    }
+}
+
+fn chest_ui_startup(mut commands: Commands, font: Res<FontAssets>, elements: Res<SpriteAssets>) {
+    let text_style = TextStyle {
+        font: font.monogram.clone(),
+        font_size: 20.0,
+        color: Color::BLACK,
+    };
+
+    let text_at = |top: f32| Style {
+        position_type: PositionType::Absolute,
+        position: UiRect {
+            top: Val::Px(top),
+            left: Val::Px(36.),
+            ..default()
+        },
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                transform: Transform::from_xyz(0., 0., Z_UI),
+                visibility: Visibility { is_visible: false },
+                ..default()
+            },
+            ChestUINode,
+            MenuWindow,
+            SessionEntity,
+        ))
+        .with_children(|parent| {
+            // player's inventory on the left, the chest on the right
+            for (side, title, left) in [
+                (Side::Player, "Inventory", Val::Percent(2.)),
+                (Side::Chest, "Chest", Val::Px(327.)),
+            ] {
+                parent
+                    .spawn(ImageBundle {
+                        image: UiImage(elements.menu.clone()),
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                top: Val::Percent(5.),
+                                left,
+                                ..default()
+                            },
+                            size: Size::new(Val::Px(300.), Val::Px(420.)),
+                            ..default()
+                        },
+                        image_mode: ImageMode::KeepAspect,
+                        ..default()
+                    })
+                    .with_children(|panel| {
+                        panel.spawn(
+                            TextBundle::from_section(title, text_style.clone())
+                                .with_style(text_at(14.)),
+                        );
+                        for line in 0..CHEST_LINES {
+                            panel.spawn((
+                                TextBundle::from_section(String::new(), text_style.clone())
+                                    .with_style(text_at(38. + line as f32 * 19.)),
+                                ChestSlotText { side, line },
+                            ));
+                        }
+                    });
+            }
+        });
+}
+
+fn ui_chest_update(
+    open: Res<OpenChest>,
+    cursor: Res<ChestCursor>,
+    item_db: Res<ItemDatabase>,
+    inventories: Query<&Inventory>,
+    player_q: Query<Entity, With<Player>>,
+    mut menu: Query<&mut Visibility, With<ChestUINode>>,
+    mut lines: Query<(&mut Text, &ChestSlotText)>,
+) {
+    menu.single_mut().is_visible = open.0.is_some();
+    let chest = match open.0 {
+        Some(chest) => chest,
+        None => return,
+    };
+    let (player_inv, chest_inv) = match (inventories.get(player_q.single()), inventories.get(chest))
+    {
+        (Ok(player_inv), Ok(chest_inv)) => (player_inv, chest_inv),
+        _ => return,
+    };
+
+    for (mut text, slot) in lines.iter_mut() {
+        let (inventory, selected) = match slot.side {
+            Side::Player => (player_inv, cursor.player),
+            Side::Chest => (chest_inv, cursor.chest),
+        };
+        // scrolls once the cursor goes past the last line
+        let index = selected.saturating_sub(CHEST_LINES - 1) + slot.line;
+        let section = &mut text.sections[0];
+        match inventory.items.get(index) {
+            Some(item) => {
+                let marker = if index == selected && slot.side == cursor.side {
+                    ">"
+                } else {
+                    " "
+                };
+                section.value = format!(
+                    "{}{: <16}{:>4}",
+                    marker,
+                    item_db.display_name(item.id),
+                    item.amt
+                );
+            }
+            None => section.value = String::new(),
+        }
+    }
+}
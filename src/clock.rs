@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+
+const DAY_LENGTH: f32 = 600.; // seconds of play for a whole day
+const DAWN: f32 = 0.25;
+const DUSK: f32 = 0.75;
+// how long the sky takes to go from light to dark, as a part of the day
+const TWILIGHT: f32 = 0.05;
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldClock>()
+            .add_event::<Dawn>()
+            .add_event::<Dusk>()
+            .add_system_set(SystemSet::on_update(AppState::InGame).with_system(tick_clock));
+    }
+}
+
+/// Time in the world, it only moves while the game is being played
+#[derive(Resource, Clone, Copy, Serialize, Deserialize)]
+pub struct WorldClock {
+    pub day: u32,
+    /// How far through the day it is, 0 is midnight and 0.5 is noon
+    pub time: f32,
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        // a new world starts in the morning
+        WorldClock {
+            day: 0,
+            time: DAWN + TWILIGHT,
+        }
+    }
+}
+
+impl WorldClock {
+    pub fn is_night(&self) -> bool {
+        !(DAWN..DUSK).contains(&self.time)
+    }
+
+    /// How dark the sky is, 0 in the day and 1 in the middle of the night with a
+    /// fade in between around dawn and dusk
+    pub fn night(&self) -> f32 {
+        let from_dawn = (self.time - DAWN) / TWILIGHT;
+        let to_dusk = (DUSK - self.time) / TWILIGHT;
+        1. - from_dawn.min(to_dusk).clamp(0., 1.)
+    }
+}
+
+//Events
+pub struct Dawn;
+
+pub struct Dusk;
+
+fn tick_clock(
+    time: Res<Time>,
+    mut clock: ResMut<WorldClock>,
+    mut ev_dawn: EventWriter<Dawn>,
+    mut ev_dusk: EventWriter<Dusk>,
+) {
+    let before = clock.time;
+    clock.time += time.delta_seconds() / DAY_LENGTH;
+    if clock.time >= 1. {
+        clock.time -= 1.;
+        clock.day += 1;
+    }
+
+    let crossed = |mark: f32| {
+        if before <= clock.time {
+            before < mark && mark <= clock.time
+        } else {
+            // wrapped past midnight
+            before < mark || mark <= clock.time
+        }
+    };
+    if crossed(DAWN) {
+        ev_dawn.send(Dawn);
+    }
+    if crossed(DUSK) {
+        ev_dusk.send(Dusk);
+    }
+}
@@ -0,0 +1,311 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use bevy::{input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+const BINDINGS_PATH: &str = "config/bindings.ron";
+// how far a stick has to be pushed before it counts as a press
+const STICK_DEADZONE: f32 = 0.5;
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bindings::load())
+            .init_resource::<Actions>()
+            .add_system_to_stage(CoreStage::PreUpdate, read_actions.after(InputSystem));
+    }
+}
+
+/// Everything the player can do, systems ask for these instead of specific keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Attack,
+    Interact,
+    Place,
+    PickUp,
+    Drop,
+    /// Held down to make drops and chest transfers take the whole stack
+    WholeStack,
+    OpenInventory,
+    Craft,
+    PrevRecipe,
+    NextRecipe,
+    SplitStack,
+    MergeStacks,
+    Hotbar(u8),
+    HotbarPrev,
+    HotbarNext,
+    ToggleZoomLock,
+    Save,
+    Load,
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight,
+    Confirm,
+    Back,
+    ExitToMenu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+    /// A stick pushed one way past the deadzone
+    Stick {
+        axis: GamepadAxisType,
+        positive: bool,
+    },
+}
+
+impl Binding {
+    fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Button(button) => format!("{:?}", button),
+            Binding::Stick { axis, positive } => {
+                format!("{:?}{}", axis, if *positive { "+" } else { "-" })
+            }
+        }
+    }
+}
+
+/// Which keys and buttons trigger each action, read from the config file
+#[derive(Resource, Serialize, Deserialize)]
+pub struct Bindings(pub BTreeMap<Action, Vec<Binding>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::{Button, Key};
+        use GamepadButtonType as Pad;
+
+        let stick = |axis, positive| Binding::Stick { axis, positive };
+        let up = stick(GamepadAxisType::LeftStickY, true);
+        let down = stick(GamepadAxisType::LeftStickY, false);
+        let left = stick(GamepadAxisType::LeftStickX, false);
+        let right = stick(GamepadAxisType::LeftStickX, true);
+        let aim_up = stick(GamepadAxisType::RightStickY, true);
+        let aim_down = stick(GamepadAxisType::RightStickY, false);
+
+        let mut bindings = BTreeMap::from([
+            (
+                Action::MoveUp,
+                vec![Key(KeyCode::W), Button(Pad::DPadUp), up],
+            ),
+            (
+                Action::MoveDown,
+                vec![Key(KeyCode::S), Button(Pad::DPadDown), down],
+            ),
+            (
+                Action::MoveLeft,
+                vec![Key(KeyCode::A), Button(Pad::DPadLeft), left],
+            ),
+            (
+                Action::MoveRight,
+                vec![Key(KeyCode::D), Button(Pad::DPadRight), right],
+            ),
+            (
+                Action::Attack,
+                vec![Key(KeyCode::Space), Button(Pad::South)],
+            ),
+            (Action::Interact, vec![Key(KeyCode::E), Button(Pad::West)]),
+            (
+                Action::Place,
+                vec![Key(KeyCode::F), Button(Pad::RightTrigger2)],
+            ),
+            (
+                Action::PickUp,
+                vec![Key(KeyCode::R), Button(Pad::LeftTrigger2)],
+            ),
+            (Action::Drop, vec![Key(KeyCode::Q), Button(Pad::Select)]),
+            (
+                Action::WholeStack,
+                vec![
+                    Key(KeyCode::LShift),
+                    Key(KeyCode::RShift),
+                    Button(Pad::LeftThumb),
+                ],
+            ),
+            (
+                Action::OpenInventory,
+                vec![Key(KeyCode::X), Button(Pad::North)],
+            ),
+            (
+                Action::Craft,
+                vec![Key(KeyCode::C), Button(Pad::RightThumb)],
+            ),
+            (Action::PrevRecipe, vec![Key(KeyCode::Up), aim_up]),
+            (Action::NextRecipe, vec![Key(KeyCode::Down), aim_down]),
+            (Action::SplitStack, vec![Key(KeyCode::H)]),
+            (Action::MergeStacks, vec![Key(KeyCode::M)]),
+            (Action::HotbarPrev, vec![Button(Pad::LeftTrigger)]),
+            (Action::HotbarNext, vec![Button(Pad::RightTrigger)]),
+            // zooming is only on the mouse wheel anyway
+            (Action::ToggleZoomLock, vec![Key(KeyCode::L)]),
+            (Action::Save, vec![Key(KeyCode::F5)]),
+            (Action::Load, vec![Key(KeyCode::F9)]),
+            // no letters, the seed gets typed into a menu
+            (
+                Action::MenuUp,
+                vec![Key(KeyCode::Up), Button(Pad::DPadUp), up],
+            ),
+            (
+                Action::MenuDown,
+                vec![Key(KeyCode::Down), Button(Pad::DPadDown), down],
+            ),
+            (
+                Action::MenuLeft,
+                vec![Key(KeyCode::Left), Button(Pad::DPadLeft), left],
+            ),
+            (
+                Action::MenuRight,
+                vec![Key(KeyCode::Right), Button(Pad::DPadRight), right],
+            ),
+            (
+                Action::Confirm,
+                vec![
+                    Key(KeyCode::Return),
+                    Key(KeyCode::Space),
+                    Button(Pad::South),
+                ],
+            ),
+            (Action::Back, vec![Key(KeyCode::Escape), Button(Pad::East)]),
+            (
+                Action::ExitToMenu,
+                vec![Key(KeyCode::F10), Button(Pad::Start)],
+            ),
+        ]);
+
+        let number_keys = [
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+            KeyCode::Key9,
+        ];
+        for (slot, key) in number_keys.into_iter().enumerate() {
+            bindings.insert(Action::Hotbar(slot as u8), vec![Key(key)]);
+        }
+
+        Bindings(bindings)
+    }
+}
+
+impl Bindings {
+    /// Reads the config file and writes it back out with the defaults filled in, so
+    /// there is something to edit the first time and actions added since show up in it.
+    /// A file that can't be read is left alone for the player to fix
+    pub fn load() -> Self {
+        let mut bindings = Bindings::default();
+        match fs::read_to_string(BINDINGS_PATH) {
+            Ok(text) => match ron::from_str::<Bindings>(&text) {
+                Ok(loaded) => {
+                    bindings.0.extend(loaded.0);
+                    bindings.save();
+                }
+                Err(err) => warn!("Could not read {}: {}", BINDINGS_PATH, err),
+            },
+            Err(_) => bindings.save(),
+        }
+        bindings
+    }
+
+    /// What to show the player for an action, a key if it has one since those are
+    /// always there and a gamepad might not be
+    pub fn label(&self, action: Action) -> String {
+        let bound = self
+            .0
+            .get(&action)
+            .map_or(&[][..], |bound| bound.as_slice());
+        bound
+            .iter()
+            .find(|binding| matches!(binding, Binding::Key(_)))
+            .or_else(|| bound.first())
+            .map_or_else(|| "(unbound)".to_string(), |binding| binding.name())
+    }
+
+    pub fn save(&self) {
+        let text = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("Could not serialize bindings: {}", err);
+                return;
+            }
+        };
+        if let Some(dir) = Path::new(BINDINGS_PATH).parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(err) = fs::write(BINDINGS_PATH, text) {
+            warn!("Could not write {}: {}", BINDINGS_PATH, err);
+        }
+    }
+}
+
+/// The actions being held down this frame and the ones that just started
+#[derive(Resource, Default)]
+pub struct Actions {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+}
+
+impl Actions {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Uses up a press so later systems this frame don't act on it too
+    pub fn clear_just_pressed(&mut self, action: Action) {
+        self.just_pressed.remove(&action);
+    }
+}
+
+// Rebuilt every frame right after bevy has read the devices, every connected
+// gamepad counts
+fn read_actions(
+    keeb_input: Res<Input<KeyCode>>,
+    pad_input: Res<Input<GamepadButton>>,
+    pad_axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    bindings: Res<Bindings>,
+    mut actions: ResMut<Actions>,
+) {
+    let is_held = |binding: &Binding| match *binding {
+        Binding::Key(key) => keeb_input.pressed(key),
+        Binding::Button(button) => gamepads
+            .iter()
+            .any(|pad| pad_input.pressed(GamepadButton::new(pad, button))),
+        Binding::Stick { axis, positive } => gamepads.iter().any(|pad| {
+            let value = pad_axes.get(GamepadAxis::new(pad, axis)).unwrap_or(0.);
+            match positive {
+                true => value > STICK_DEADZONE,
+                false => value < -STICK_DEADZONE,
+            }
+        }),
+    };
+
+    let pressed: HashSet<Action> = bindings
+        .0
+        .iter()
+        .filter(|(_, bound)| bound.iter().any(is_held))
+        .map(|(action, _)| *action)
+        .collect();
+    actions.just_pressed = pressed.difference(&actions.pressed).copied().collect();
+    actions.pressed = pressed;
+}
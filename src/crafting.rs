@@ -0,0 +1,330 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    ui::widget::ImageMode,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::{
+    assetload::DataAssets,
+    controls::{Action, Actions},
+    inventory::{Inventory, InventoryUpdate, MenuWindow, PlayerMenu},
+    item::Item,
+    item_database::ItemDatabase,
+    player::Player,
+    AppState, FontAssets, SessionEntity, SpriteAssets,
+};
+
+const Z_UI: f32 = 80.;
+const RECIPE_LINES: usize = 8;
+const INPUT_LINES: usize = 3;
+
+pub struct CraftingPlugin;
+
+impl Plugin for CraftingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<RecipeBook>()
+            .init_asset_loader::<RecipeBookLoader>()
+            .init_resource::<CraftingCursor>()
+            .add_event::<CraftSuccess>()
+            .add_system_set(SystemSet::on_exit(AppState::AssetLoad).with_system(insert_recipes))
+            .add_system_set(
+                SystemSet::on_enter(AppState::GameLoad).with_system(crafting_ui_startup),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(crafting_input)
+                    .with_system(ui_crafting_update.after(crafting_input)),
+            );
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RecipeDef {
+    inputs: Vec<(String, u32)>,
+    output: (String, u32),
+}
+
+/// Recipes as they are written in assets/data/recipes.ron
+#[derive(TypeUuid)]
+#[uuid = "0d6f3a44-5b8e-4c8a-a1f4-7c3e2b9d6f10"]
+pub struct RecipeBook(pub Vec<RecipeDef>);
+
+pub struct Recipe {
+    pub inputs: Vec<Item>,
+    pub output: Item,
+}
+
+/// Every recipe with its items looked up in the item database
+#[derive(Resource)]
+pub struct Recipes(pub Vec<Recipe>);
+
+#[derive(Debug)]
+pub enum CraftError {
+    MissingInputs,
+    NoRoom,
+}
+
+impl Recipe {
+    pub fn can_craft(&self, inventory: &Inventory) -> bool {
+        self.inputs
+            .iter()
+            .all(|input| inventory.count_of(input.id) >= input.amt)
+    }
+}
+
+/// Takes the inputs out of the inventory and puts the output in, either all of it
+/// happens or none of it does
+pub fn craft(
+    inventory: &mut Inventory,
+    recipe: &Recipe,
+    item_db: &ItemDatabase,
+) -> Result<(), CraftError> {
+    if !recipe.can_craft(inventory) {
+        return Err(CraftError::MissingInputs);
+    }
+
+    // worked out on a copy since using up the inputs can free a slot for the output
+    let mut crafted = inventory.clone();
+    for input in recipe.inputs.iter() {
+        crafted.remove(input.id, input.amt);
+    }
+    if !crafted.add(recipe.output.clone(), item_db) {
+        return Err(CraftError::NoRoom);
+    }
+
+    *inventory = crafted;
+    Ok(())
+}
+
+//Events
+pub struct CraftSuccess;
+
+#[derive(Resource, Default)]
+struct CraftingCursor(usize);
+
+#[derive(Component)]
+struct CraftingUINode;
+
+#[derive(Component)]
+struct RecipeLine(usize);
+
+#[derive(Component)]
+struct InputLine(usize);
+
+fn insert_recipes(
+    mut commands: Commands,
+    data: Res<DataAssets>,
+    books: Res<Assets<RecipeBook>>,
+    item_dbs: Res<Assets<ItemDatabase>>,
+) {
+    let book = books
+        .get(&data.recipes)
+        .expect("The recipes should be loaded by now");
+    let item_db = item_dbs
+        .get(&data.items)
+        .expect("The item database should be loaded by now");
+
+    let recipes = book
+        .0
+        .iter()
+        .map(|def| Recipe {
+            inputs: def
+                .inputs
+                .iter()
+                .map(|(name, amt)| item_db.item(name, *amt))
+                .collect(),
+            output: item_db.item(&def.output.0, def.output.1),
+        })
+        .collect();
+    commands.insert_resource(Recipes(recipes));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn crafting_input(
+    actions: Res<Actions>,
+    menu: Query<&Visibility, With<CraftingUINode>>,
+    recipes: Res<Recipes>,
+    item_db: Res<ItemDatabase>,
+    mut cursor: ResMut<CraftingCursor>,
+    mut player_q: Query<&mut Inventory, With<Player>>,
+    mut ev_crafted: EventWriter<CraftSuccess>,
+    mut ev_inventory_update: EventWriter<InventoryUpdate>,
+) {
+    if !menu.single().is_visible || recipes.0.is_empty() {
+        return;
+    }
+
+    let count = recipes.0.len();
+    if actions.just_pressed(Action::PrevRecipe) {
+        cursor.0 = (cursor.0 + count - 1) % count;
+    }
+    if actions.just_pressed(Action::NextRecipe) {
+        cursor.0 = (cursor.0 + 1) % count;
+    }
+
+    if actions.just_pressed(Action::Craft) {
+        let mut inventory = player_q.single_mut();
+        match craft(&mut inventory, &recipes.0[cursor.0], &item_db) {
+            Ok(()) => {
+                ev_crafted.send(CraftSuccess);
+                ev_inventory_update.send(InventoryUpdate);
+            }
+            Err(err) => info!("Could not craft: {:?}", err),
+        }
+    }
+}
+
+fn crafting_ui_startup(mut commands: Commands, font: Res<FontAssets>, elements: Res<SpriteAssets>) {
+    let text_style = TextStyle {
+        font: font.monogram.clone(),
+        font_size: 20.0,
+        color: Color::BLACK,
+    };
+
+    let line_style = |top: f32| Style {
+        position_type: PositionType::Absolute,
+        position: UiRect {
+            top: Val::Px(top),
+            left: Val::Px(26.),
+            ..default()
+        },
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                transform: Transform::from_xyz(0., 0., Z_UI),
+                visibility: Visibility { is_visible: false }, // opened with the inventory
+                ..default()
+            },
+            CraftingUINode,
+            PlayerMenu,
+            MenuWindow,
+            SessionEntity,
+        ))
+        .with_children(|parent| {
+            // sits to the right of the inventory window
+            parent
+                .spawn(ImageBundle {
+                    image: UiImage(elements.menu.clone()),
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            top: Val::Percent(5.),
+                            left: Val::Px(420.),
+                            ..default()
+                        },
+                        size: Size::new(Val::Px(212.), Val::Px(297.)),
+                        ..default()
+                    },
+                    image_mode: ImageMode::KeepAspect,
+                    ..default()
+                })
+                .with_children(|craft_parent| {
+                    for i in 0..RECIPE_LINES {
+                        craft_parent.spawn((
+                            TextBundle::from_section(String::new(), text_style.clone())
+                                .with_style(line_style(12. + i as f32 * 18.)),
+                            RecipeLine(i),
+                        ));
+                    }
+                    // what the selected recipe needs
+                    for i in 0..INPUT_LINES {
+                        craft_parent.spawn((
+                            TextBundle::from_section(String::new(), text_style.clone())
+                                .with_style(line_style(180. + i as f32 * 18.)),
+                            InputLine(i),
+                        ));
+                    }
+                });
+        });
+}
+
+fn ui_crafting_update(
+    mut ev_invopen: EventReader<InventoryUpdate>,
+    cursor: Res<CraftingCursor>,
+    recipes: Res<Recipes>,
+    item_db: Res<ItemDatabase>,
+    player_q: Query<&Inventory, With<Player>>,
+    mut recipe_lines: Query<(&mut Text, &RecipeLine), Without<InputLine>>,
+    mut input_lines: Query<(&mut Text, &InputLine), Without<RecipeLine>>,
+) {
+    if ev_invopen.iter().last().is_none() && !cursor.is_changed() {
+        return;
+    }
+    let inventory = match player_q.get_single() {
+        Ok(inv) => inv,
+        Err(_) => return,
+    };
+
+    // scrolls once the cursor goes past the last line
+    let first = cursor.0.saturating_sub(RECIPE_LINES - 1);
+    for (mut text, line) in recipe_lines.iter_mut() {
+        let index = first + line.0;
+        let section = &mut text.sections[0];
+        match recipes.0.get(index) {
+            Some(recipe) => {
+                let prefix = if index == cursor.0 { "> " } else { "  " };
+                section.value = format!(
+                    "{}{} x{}",
+                    prefix,
+                    item_db.display_name(recipe.output.id),
+                    recipe.output.amt
+                );
+                section.style.color = if recipe.can_craft(inventory) {
+                    Color::BLACK
+                } else {
+                    Color::GRAY
+                };
+            }
+            None => section.value = String::new(),
+        }
+    }
+
+    let selected = recipes.0.get(cursor.0);
+    for (mut text, line) in input_lines.iter_mut() {
+        let section = &mut text.sections[0];
+        match selected.and_then(|recipe| recipe.inputs.get(line.0)) {
+            Some(input) => {
+                let have = inventory.count_of(input.id);
+                section.value = format!(
+                    "{}/{} {}",
+                    have.min(input.amt),
+                    input.amt,
+                    item_db.display_name(input.id)
+                );
+                section.style.color = if have >= input.amt {
+                    Color::BLACK
+                } else {
+                    Color::MAROON
+                };
+            }
+            None => section.value = String::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RecipeBookLoader;
+
+impl AssetLoader for RecipeBookLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let defs = ron::de::from_bytes::<Vec<RecipeDef>>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(RecipeBook(defs)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["recipes.ron"]
+    }
+}
@@ -0,0 +1,161 @@
+use bevy::{prelude::*, ui::widget::ImageMode};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    controls::{Action, Actions, Bindings},
+    health::{Health, Invulnerable, PlayerDied, Stamina},
+    level::{ChangeLevel, CurrentLevel, SURFACE_LEVEL},
+    player::{Player, SpawnPoint},
+    AppState, FontAssets, SpriteAssets,
+};
+
+const RESPAWN_INVULNERABILITY: f32 = 2.0;
+
+pub struct DeathPlugin;
+
+impl Plugin for DeathPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(AppState::InGame).with_system(player_death))
+            .add_system_set(SystemSet::on_enter(AppState::Dead).with_system(spawn_death_screen))
+            .add_system_set(SystemSet::on_update(AppState::Dead).with_system(death_screen_input))
+            .add_system_set(SystemSet::on_exit(AppState::Dead).with_system(despawn_death_screen));
+    }
+}
+
+#[derive(Component)]
+struct DeathScreen;
+
+fn player_death(mut ev_player_died: EventReader<PlayerDied>, mut state: ResMut<State<AppState>>) {
+    if ev_player_died.iter().next().is_some() {
+        // pushed so the world stays as it was behind the death screen,
+        // dying wins over anything else that was queued this frame
+        state
+            .overwrite_push(AppState::Dead)
+            .expect("Failed to change states");
+    }
+}
+
+// Confirming brings the player back at the spawn point, otherwise they can give up
+// and go to the menu
+type RespawnQuery<'a> = (
+    &'a mut Transform,
+    &'a mut Velocity,
+    &'a mut Health,
+    &'a mut Stamina,
+);
+fn death_screen_input(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    mut state: ResMut<State<AppState>>,
+    mut player_q: Query<(Entity, RespawnQuery), With<Player>>,
+    spawn: Res<SpawnPoint>,
+    level: Res<CurrentLevel>,
+    mut ev_change_level: EventWriter<ChangeLevel>,
+) {
+    if actions.just_pressed(Action::ExitToMenu) {
+        // unlike leaving from the game this doesn't save, a save of a dead player
+        // would load them back up where they fell at full health, so giving up
+        // keeps the last save as it was
+        state
+            .replace(AppState::MainMenu)
+            .expect("Failed to change states");
+        return;
+    }
+    if !actions.just_pressed(Action::Confirm) {
+        return;
+    }
+
+    let (player, (mut transform, mut velocity, mut health, mut stamina)) = player_q.single_mut();
+    transform.translation.x = spawn.0.x;
+    transform.translation.y = spawn.0.y;
+    velocity.linvel = Vec2::ZERO;
+    health.current = health.max;
+    stamina.refill();
+    // the spawn point is on the surface, wherever the player fell
+    if level.0 != SURFACE_LEVEL {
+        ev_change_level.send(ChangeLevel {
+            level: SURFACE_LEVEL,
+            pos: spawn.0,
+        });
+    }
+    // a moment to get away from whatever is waiting at the spawn
+    commands
+        .entity(player)
+        .insert(Invulnerable::for_seconds(RESPAWN_INVULNERABILITY));
+
+    state.pop().expect("Failed to change states");
+}
+
+fn spawn_death_screen(
+    mut commands: Commands,
+    font: Res<FontAssets>,
+    elements: Res<SpriteAssets>,
+    bindings: Res<Bindings>,
+) {
+    let title_style = TextStyle {
+        font: font.celtic.clone(),
+        font_size: 40.0,
+        color: Color::BLACK,
+    };
+    let line_style = TextStyle {
+        font: font.monogram.clone(),
+        font_size: 28.0,
+        color: Color::BLACK,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            DeathScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(ImageBundle {
+                    image: UiImage(elements.menu.clone()),
+                    image_mode: ImageMode::KeepAspect,
+                    style: Style {
+                        size: Size::new(Val::Px(280.), Val::Px(200.)),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(24.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|screen_parent| {
+                    screen_parent.spawn(
+                        TextBundle::from_section("You Died", title_style).with_style(Style {
+                            margin: UiRect::all(Val::Px(16.)),
+                            ..default()
+                        }),
+                    );
+                    // the keys can be rebound so the lines say whatever they are now
+                    let lines = [
+                        format!("{} to respawn", bindings.label(Action::Confirm)),
+                        format!("{} for menu", bindings.label(Action::ExitToMenu)),
+                    ];
+                    for line in lines {
+                        screen_parent.spawn(
+                            TextBundle::from_section(line, line_style.clone()).with_style(Style {
+                                margin: UiRect::all(Val::Px(4.)),
+                                ..default()
+                            }),
+                        );
+                    }
+                });
+        });
+}
+
+fn despawn_death_screen(mut commands: Commands, screen: Query<Entity, With<DeathScreen>>) {
+    for entity in screen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
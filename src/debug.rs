@@ -20,8 +20,7 @@ impl Plugin for DebugPlugin {
         if cfg!(debug_assertions) {
             app.add_plugin(LogDiagnosticsPlugin::default())
                 // .add_plugin(FrameTimeDiagnosticsPlugin::default())
-                .add_plugin(InspectorPlugin::<Data>::new())
-                .add_system(bevy::window::close_on_esc);
+                .add_plugin(InspectorPlugin::<Data>::new());
         }
     }
 }
@@ -0,0 +1,269 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+
+use crate::{
+    controls::{Action, Actions},
+    health::Stamina,
+    inventory::{ActiveItem, MenuWindow},
+    item::spawn_ground_item,
+    item_database::{ItemDatabase, ToolKind},
+    map_generation::{
+        tile_to_world, world_to_tile, Chunk, WorldEdits, WorldGenerator, CHUNK_SIZE, TILE_SIZE,
+    },
+    player::{Direction, PSystems, Player},
+    AppState, SpriteAssets,
+};
+
+const Z_DECORATION: f32 = 30.;
+
+pub struct DecorationPlugin;
+
+impl Plugin for DecorationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HarvestedDecorations>().add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(decorate_chunks)
+                .with_system(
+                    harvest_decoration
+                        .label(PSystems::Harvest)
+                        .after(PSystems::Attack),
+                ),
+        );
+    }
+}
+
+/// Things scattered over the world by generation, which ones grow where is
+/// decided by the biome
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decoration {
+    Tree,
+    Bush,
+    Flower,
+    Cactus,
+    IronOre,
+}
+
+/// The tile a decoration was generated on
+#[derive(Component)]
+pub struct DecorationTile(pub IVec2);
+
+/// Hits a decoration has taken so far, lost when its chunk is despawned
+#[derive(Component)]
+struct HarvestDamage(u32);
+
+/// Tiles whose decoration has been harvested, generation leaves them empty
+#[derive(Resource, Default)]
+pub struct HarvestedDecorations(pub HashSet<IVec2>);
+
+// Sprites are items from the test sheet tinted until there are proper ones
+struct Look {
+    sprite: usize,
+    color: Color,
+    size: f32,
+}
+
+/// How a decoration holds up to being hit and what it leaves behind,
+/// drops are (item, most dropped, chance of dropping any)
+struct Harvest {
+    health: u32,
+    tool: ToolKind,
+    drops: &'static [(&'static str, u32, f64)],
+}
+
+impl Decoration {
+    fn look(&self) -> Look {
+        let (sprite, color, size) = match self {
+            Decoration::Tree => (4, Color::rgb(0.1, 0.45, 0.15), TILE_SIZE),
+            Decoration::Bush => (4, Color::rgb(0.3, 0.6, 0.2), 10.),
+            Decoration::Flower => (2, Color::rgb(1.0, 0.85, 0.2), 6.),
+            Decoration::Cactus => (4, Color::rgb(0.35, 0.75, 0.3), 14.),
+            Decoration::IronOre => (2, Color::rgb(0.7, 0.45, 0.35), TILE_SIZE),
+        };
+        Look {
+            sprite,
+            color,
+            size,
+        }
+    }
+
+    /// Solid decorations block movement like a wall
+    pub fn is_solid(&self) -> bool {
+        matches!(
+            self,
+            Decoration::Tree | Decoration::Cactus | Decoration::IronOre
+        )
+    }
+
+    fn harvest(&self) -> Option<Harvest> {
+        match self {
+            Decoration::Tree => Some(Harvest {
+                health: 8,
+                tool: ToolKind::Axe,
+                drops: &[("wood", 3, 1.0), ("apple", 1, 0.3)],
+            }),
+            Decoration::IronOre => Some(Harvest {
+                health: 16,
+                tool: ToolKind::Pickaxe,
+                drops: &[("iron_ore", 2, 1.0), ("pebble", 2, 0.5)],
+            }),
+            _ => None,
+        }
+    }
+}
+
+// Chunks get their decorations as children when they spawn so they go away together
+fn decorate_chunks(
+    mut commands: Commands,
+    chunks: Query<(Entity, &Chunk), Added<Chunk>>,
+    generator: Res<WorldGenerator>,
+    edits: Res<WorldEdits>,
+    harvested: Res<HarvestedDecorations>,
+    sprites: Res<SpriteAssets>,
+) {
+    let size = CHUNK_SIZE as i32;
+    for (chunk_entity, chunk) in chunks.iter() {
+        let origin = chunk.pos * size;
+        let mut children = vec![];
+        for x in 0..size {
+            for y in 0..size {
+                let tile = origin + IVec2::new(x, y);
+                let decoration = match generator.decoration_at(tile) {
+                    Some(decoration) => decoration,
+                    None => continue,
+                };
+                // nothing grows back where it was harvested or where the ground changed
+                if harvested.0.contains(&tile) || !edits.tile_at(&generator, tile).is_walkable() {
+                    continue;
+                }
+                children.push(spawn_decoration(
+                    &mut commands,
+                    &sprites,
+                    decoration,
+                    tile,
+                    tile_to_world(tile - origin),
+                ));
+            }
+        }
+        commands.entity(chunk_entity).push_children(&children);
+    }
+}
+
+fn spawn_decoration(
+    commands: &mut Commands,
+    sprites: &SpriteAssets,
+    decoration: Decoration,
+    tile: IVec2,
+    local: Vec2,
+) -> Entity {
+    let look = decoration.look();
+    let entity = commands
+        .spawn((
+            SpriteSheetBundle {
+                texture_atlas: sprites.items.clone(),
+                sprite: TextureAtlasSprite {
+                    index: look.sprite,
+                    color: look.color,
+                    custom_size: Some(Vec2::splat(look.size)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(local.x, local.y, Z_DECORATION),
+                ..default()
+            },
+            decoration,
+            DecorationTile(tile),
+        ))
+        .id();
+    if decoration.is_solid() {
+        commands.entity(entity).insert((
+            RigidBody::Fixed,
+            Collider::cuboid(TILE_SIZE / 2. - 2., TILE_SIZE / 2. - 2.),
+        ));
+    }
+    entity
+}
+
+type HarvestTargetQuery<'a> = (
+    Entity,
+    &'a Decoration,
+    &'a DecorationTile,
+    &'a mut TextureAtlasSprite,
+    Option<&'a mut HarvestDamage>,
+);
+// Swinging at a tree or ore in front of the player chops away at it, works just
+// like mining a tile and takes the press before mining sees it
+#[allow(clippy::too_many_arguments)]
+fn harvest_decoration(
+    mut commands: Commands,
+    mut actions: ResMut<Actions>,
+    mut player_q: Query<(&Transform, &Direction, &ActiveItem, &mut Stamina), With<Player>>,
+    mut decorations: Query<HarvestTargetQuery>,
+    mut harvested: ResMut<HarvestedDecorations>,
+    item_db: Res<ItemDatabase>,
+    sprites: Res<SpriteAssets>,
+    menus: Query<&Visibility, With<MenuWindow>>,
+) {
+    if !actions.just_pressed(Action::Attack) || menus.iter().any(|menu| menu.is_visible) {
+        return;
+    }
+
+    let (transform, direction, active, mut stamina) = player_q.single_mut();
+    let target = world_to_tile(transform.translation.truncate()) + direction.offset();
+    let (entity, decoration, _, mut sprite, damage) = match decorations
+        .iter_mut()
+        .find(|(_, _, tile, ..)| tile.0 == target)
+    {
+        Some(found) => found,
+        None => return,
+    };
+    let harvest = match decoration.harvest() {
+        Some(harvest) => harvest,
+        None => return,
+    };
+
+    actions.clear_just_pressed(Action::Attack);
+    if !stamina.try_use(1) {
+        return;
+    }
+
+    let hit = match active.tool(&item_db, harvest.tool) {
+        Some(tool) => 1 + tool.power,
+        None => 1,
+    };
+    let total = damage.as_ref().map_or(0, |damage| damage.0) + hit;
+
+    if total < harvest.health {
+        // darken a little more with every hit
+        let shade = 1. - 0.5 * total as f32 / harvest.health as f32;
+        let base = decoration.look().color;
+        sprite.color = Color::rgb(base.r() * shade, base.g() * shade, base.b() * shade);
+        match damage {
+            Some(mut damage) => damage.0 = total,
+            None => {
+                commands.entity(entity).insert(HarvestDamage(total));
+            }
+        }
+        return;
+    }
+
+    commands.entity(entity).despawn_recursive();
+    harvested.0.insert(target);
+
+    let mut rng = rand::thread_rng();
+    let center = tile_to_world(target);
+    for (name, max, chance) in harvest.drops {
+        if !rng.gen_bool(*chance) {
+            continue;
+        }
+        for _ in 0..rng.gen_range(1..=*max) {
+            let jitter = Vec2::new(rng.gen_range(-4.0..4.0), rng.gen_range(-4.0..4.0));
+            spawn_ground_item(
+                &mut commands,
+                &sprites,
+                &item_db,
+                item_db.item(name, 1),
+                center + jitter,
+            );
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    chest::spawn_chest,
+    controls::{Action, Actions},
+    decoration::DecorationTile,
+    inventory::{ActiveItem, Inventory, InventoryUpdate, MenuWindow},
+    item::Item,
+    item_database::{ItemDatabase, ItemId},
+    map_generation::{tile_to_world, world_to_tile, LoadedChunks, TILE_SIZE},
+    player::{Direction, Player},
+    tile_type::TileType,
+    AppState, SessionEntity, SpriteAssets,
+};
+
+const Z_FURNITURE: f32 = 38.;
+
+pub struct FurniturePlugin;
+
+impl Plugin for FurniturePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(place_furniture)
+                .with_system(pick_up_furniture),
+        );
+    }
+}
+
+/// Placed from an item and turned back into that item when it's picked up
+#[derive(Component)]
+pub struct Furniture {
+    pub item: ItemId,
+}
+
+pub fn spawn_furniture(
+    commands: &mut Commands,
+    sprites: &SpriteAssets,
+    item_db: &ItemDatabase,
+    item: ItemId,
+    pos: Vec2,
+) -> Entity {
+    let (index, color) = match item_db.get(item) {
+        Some(def) => (def.sprite_index, def.color()),
+        None => (0, Color::FUCHSIA),
+    };
+
+    commands
+        .spawn((
+            SpriteSheetBundle {
+                texture_atlas: sprites.items.clone(),
+                sprite: TextureAtlasSprite {
+                    index,
+                    color,
+                    custom_size: Some(Vec2::splat(TILE_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(pos.x, pos.y, Z_FURNITURE),
+                ..default()
+            },
+            Furniture { item },
+            // only the player outranks furniture, so mobs can't shove it around,
+            // and the damping stops it as soon as the player stops pushing
+            RigidBody::Dynamic,
+            Velocity::zero(),
+            Damping {
+                linear_damping: 20.,
+                angular_damping: 0.,
+            },
+            LockedAxes::ROTATION_LOCKED,
+            Collider::cuboid(TILE_SIZE / 2. - 1., TILE_SIZE / 2. - 1.),
+            SessionEntity,
+        ))
+        .id()
+}
+
+/// Whether something is right in front of the player, furniture gets pushed
+/// off of the tile grid so this goes by distance instead of by tile
+pub fn in_reach(player: &Transform, direction: &Direction, target: &Transform) -> bool {
+    let reach = player.translation.truncate() + direction.offset().as_vec2() * TILE_SIZE;
+    target.translation.truncate().distance(reach) < TILE_SIZE * 0.75
+}
+
+type PlacerQuery<'a> = (
+    &'a Transform,
+    &'a Direction,
+    &'a ActiveItem,
+    &'a mut Inventory,
+);
+// Placing puts the held piece of furniture down on the tile in front of the player,
+// as long as nothing else is already on it
+#[allow(clippy::too_many_arguments)]
+fn place_furniture(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    mut player_q: Query<PlacerQuery, (With<Player>, Without<Furniture>)>,
+    furniture: Query<&Transform, With<Furniture>>,
+    decorations: Query<&DecorationTile>,
+    loaded: Res<LoadedChunks>,
+    storages: Query<&TileStorage>,
+    types: Query<&TileType>,
+    item_db: Res<ItemDatabase>,
+    sprites: Res<SpriteAssets>,
+    menus: Query<&Visibility, With<MenuWindow>>,
+    mut ev_inventory_update: EventWriter<InventoryUpdate>,
+) {
+    if !actions.just_pressed(Action::Place) || menus.iter().any(|menu| menu.is_visible) {
+        return;
+    }
+
+    let (transform, direction, active, mut inventory) = player_q.single_mut();
+    let def = match active.item.as_ref().and_then(|item| item_db.get(item.id)) {
+        Some(def) if def.has_tag("furniture") => def,
+        _ => return,
+    };
+
+    let target = world_to_tile(transform.translation.truncate()) + direction.offset();
+    let pos = tile_to_world(target);
+    let on_solid = match loaded.tile_type(&storages, &types, target) {
+        Some(tile_type) => !tile_type.is_walkable(),
+        None => true,
+    };
+    let on_furniture = furniture
+        .iter()
+        .any(|other| other.translation.truncate().distance(pos) < TILE_SIZE);
+    // the tile in front is always in a spawned chunk, so its decoration is too
+    let on_decoration = decorations.iter().any(|decoration| decoration.0 == target);
+    if on_solid || on_furniture || on_decoration {
+        return;
+    }
+
+    inventory.take_from_slot(active.slot, 1);
+    match def.storage {
+        Some(capacity) => spawn_chest(
+            &mut commands,
+            &sprites,
+            &item_db,
+            pos,
+            Inventory::new(capacity),
+        ),
+        None => spawn_furniture(&mut commands, &sprites, &item_db, def.id, pos),
+    };
+    ev_inventory_update.send(InventoryUpdate);
+}
+
+type FurnitureQuery<'a> = (Entity, &'a Transform, &'a Furniture, Option<&'a Inventory>);
+// Picking up takes the furniture in front of the player, chests have to be emptied first
+#[allow(clippy::too_many_arguments)]
+fn pick_up_furniture(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    mut player_q: Query<
+        (&Transform, &Direction, &mut Inventory),
+        (With<Player>, Without<Furniture>),
+    >,
+    furniture: Query<FurnitureQuery, Without<Player>>,
+    item_db: Res<ItemDatabase>,
+    menus: Query<&Visibility, With<MenuWindow>>,
+    mut ev_inventory_update: EventWriter<InventoryUpdate>,
+) {
+    if !actions.just_pressed(Action::PickUp) || menus.iter().any(|menu| menu.is_visible) {
+        return;
+    }
+
+    let (transform, direction, mut inventory) = player_q.single_mut();
+    let (entity, _, piece, contents) = match furniture
+        .iter()
+        .find(|(_, target, ..)| in_reach(transform, direction, target))
+    {
+        Some(found) => found,
+        None => return,
+    };
+
+    if contents.map_or(false, |contents| !contents.items.is_empty()) {
+        info!("The chest has to be empty to pick it up");
+        return;
+    }
+    let item = Item {
+        id: piece.item,
+        amt: 1,
+    };
+    if !inventory.add(item, &item_db) {
+        warn!("Inventory is full, cannot perform action");
+        return;
+    }
+
+    commands.entity(entity).despawn_recursive();
+    ev_inventory_update.send(InventoryUpdate);
+}
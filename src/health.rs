@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{Inspectable, RegisterInspectable};
+
+use crate::{AppState, PlayerEntity};
+
+// Stamina comes back one point at a time after resting for a moment
+const STAMINA_REGEN_DELAY: f32 = 1.0;
+const STAMINA_REGEN_RATE: f32 = 0.2;
+const PLAYER_INVULNERABILITY: f32 = 1.0;
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(tick_invulnerability)
+                .with_system(regen_stamina)
+                .with_system(apply_damage)
+                .with_system(apply_heals),
+        )
+        .add_event::<DamageEvent>()
+        .add_event::<HealEvent>()
+        .add_event::<PlayerHurt>()
+        .add_event::<PlayerDied>()
+        .register_inspectable::<Health>()
+        .register_inspectable::<Stamina>();
+    }
+}
+
+#[derive(Component, Inspectable)]
+pub struct Health {
+    pub current: i32,
+    pub max: i32,
+}
+
+impl Health {
+    pub fn new(max: i32) -> Self {
+        Self { current: max, max }
+    }
+
+    /// Returns true when this was the hit that killed it
+    pub fn damage(&mut self, amount: i32) -> bool {
+        let was_alive = self.current > 0;
+        self.current = (self.current - amount).max(0);
+        was_alive && self.current == 0
+    }
+
+    pub fn heal(&mut self, amount: i32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+}
+
+/// Spent by swinging at things, refills while the entity takes a break
+#[derive(Component, Inspectable)]
+pub struct Stamina {
+    pub current: i32,
+    pub max: i32,
+    rested: f32,
+}
+
+impl Stamina {
+    pub fn new(max: i32) -> Self {
+        Self {
+            current: max,
+            max,
+            rested: 0.,
+        }
+    }
+
+    /// Spends the stamina if there is enough of it
+    pub fn try_use(&mut self, amount: i32) -> bool {
+        if self.current < amount {
+            return false;
+        }
+        self.current -= amount;
+        self.rested = 0.;
+        true
+    }
+
+    pub fn refill(&mut self) {
+        self.current = self.max;
+        self.rested = 0.;
+    }
+}
+
+/// Can't be hurt again until the timer runs out
+#[derive(Component, Deref, DerefMut)]
+pub struct Invulnerable(pub Timer);
+
+impl Invulnerable {
+    pub fn for_seconds(seconds: f32) -> Self {
+        Invulnerable(Timer::from_seconds(seconds, TimerMode::Once))
+    }
+}
+
+//Events
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: i32,
+}
+
+pub struct HealEvent {
+    pub target: Entity,
+    pub amount: i32,
+}
+
+pub struct PlayerHurt;
+
+pub struct PlayerDied;
+
+fn tick_invulnerability(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut timers: Query<(Entity, &mut Invulnerable)>,
+) {
+    for (entity, mut timer) in timers.iter_mut() {
+        if timer.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+fn regen_stamina(time: Res<Time>, mut staminas: Query<&mut Stamina>) {
+    for mut stamina in staminas.iter_mut() {
+        if stamina.current >= stamina.max {
+            continue;
+        }
+        stamina.rested += time.delta_seconds();
+        if stamina.rested >= STAMINA_REGEN_DELAY {
+            stamina.current += 1;
+            // the next point comes after a short tick instead of the full delay
+            stamina.rested = STAMINA_REGEN_DELAY - STAMINA_REGEN_RATE;
+        }
+    }
+}
+
+// Anything with health can be hurt through events, the player gets a moment
+// of invulnerability after every hit
+fn apply_damage(
+    mut commands: Commands,
+    mut ev_damage: EventReader<DamageEvent>,
+    mut targets: Query<&mut Health, Without<Invulnerable>>,
+    player_e: Res<PlayerEntity>,
+    mut ev_player_hurt: EventWriter<PlayerHurt>,
+    mut ev_player_died: EventWriter<PlayerDied>,
+) {
+    for ev in ev_damage.iter() {
+        let mut health = match targets.get_mut(ev.target) {
+            Ok(health) => health,
+            Err(_) => continue,
+        };
+        if health.current == 0 {
+            continue;
+        }
+
+        let died = health.damage(ev.amount);
+        if ev.target != player_e.0 {
+            continue;
+        }
+        commands
+            .entity(ev.target)
+            .insert(Invulnerable::for_seconds(PLAYER_INVULNERABILITY));
+        if died {
+            ev_player_died.send(PlayerDied);
+        } else {
+            ev_player_hurt.send(PlayerHurt);
+        }
+    }
+}
+
+fn apply_heals(mut ev_heal: EventReader<HealEvent>, mut targets: Query<&mut Health>) {
+    for ev in ev_heal.iter() {
+        if let Ok(mut health) = targets.get_mut(ev.target) {
+            health.heal(ev.amount);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use bevy::{input::mouse::MouseWheel, prelude::*};
+
+use crate::{
+    camera::CamScrollLock,
+    controls::{Action, Actions},
+    inventory::{ActiveItem, ActiveItemSystems, MenuWindow},
+    item_database::ItemDatabase,
+    player::{Direction, Player},
+    AppState, SpriteAssets,
+};
+
+/// The first slots of the inventory, picked with the number keys
+pub const HOTBAR_SLOTS: usize = 9;
+
+pub struct HotbarPlugin;
+
+impl Plugin for HotbarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(hotbar_input.label(ActiveItemSystems::Select))
+                .with_system(spawn_held_sprite)
+                .with_system(update_held_sprite.after(ActiveItemSystems::Update)),
+        );
+    }
+}
+
+/// The item drawn in the player's hand
+#[derive(Component)]
+struct HeldItemSprite;
+
+// Number keys jump straight to a slot, the mouse wheel or shoulder buttons step
+// through them unless the camera is using the wheel to zoom
+fn hotbar_input(
+    actions: Res<Actions>,
+    mut scroll_wheel: EventReader<MouseWheel>,
+    mut player_q: Query<&mut ActiveItem, With<Player>>,
+    camera: Query<&CamScrollLock>,
+    menus: Query<&Visibility, With<MenuWindow>>,
+) {
+    let mut active = player_q.single_mut();
+
+    if let Some(slot) =
+        (0..HOTBAR_SLOTS).find(|slot| actions.just_pressed(Action::Hotbar(*slot as u8)))
+    {
+        active.slot = slot;
+    }
+
+    // the shoulder buttons step through recipes while the menus are open
+    if menus.iter().any(|menu| menu.is_visible) {
+        return;
+    }
+    let mut step = |forward: bool| {
+        let slot = active.slot.min(HOTBAR_SLOTS - 1);
+        active.slot = match forward {
+            true => (slot + 1) % HOTBAR_SLOTS,
+            false => (slot + HOTBAR_SLOTS - 1) % HOTBAR_SLOTS,
+        };
+    };
+    if actions.just_pressed(Action::HotbarPrev) {
+        step(false);
+    }
+    if actions.just_pressed(Action::HotbarNext) {
+        step(true);
+    }
+
+    if camera.iter().any(|lock| !lock.0) {
+        return;
+    }
+    for scroll in scroll_wheel.iter() {
+        if scroll.y != 0. {
+            step(scroll.y < 0.);
+        }
+    }
+}
+
+fn spawn_held_sprite(
+    mut commands: Commands,
+    players: Query<Entity, Added<Player>>,
+    sprites: Res<SpriteAssets>,
+) {
+    for player in players.iter() {
+        commands.entity(player).with_children(|parent| {
+            parent.spawn((
+                SpriteSheetBundle {
+                    texture_atlas: sprites.items.clone(),
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                },
+                HeldItemSprite,
+            ));
+        });
+    }
+}
+
+fn update_held_sprite(
+    player_q: Query<(&Direction, &ActiveItem), With<Player>>,
+    mut held_q: Query<
+        (&mut TextureAtlasSprite, &mut Transform, &mut Visibility),
+        With<HeldItemSprite>,
+    >,
+    item_db: Res<ItemDatabase>,
+) {
+    let (direction, active) = player_q.single();
+    let (mut sprite, mut transform, mut visibility) = match held_q.get_single_mut() {
+        Ok(held) => held,
+        Err(_) => return,
+    };
+
+    let def = match active.item.as_ref().and_then(|item| item_db.get(item.id)) {
+        Some(def) => def,
+        None => {
+            visibility.is_visible = false;
+            return;
+        }
+    };
+    visibility.is_visible = true;
+    sprite.index = def.sprite_index;
+    sprite.color = def.color();
+
+    // held out to the side the player is facing, behind them when walking away
+    let (offset, flip) = match direction {
+        Direction::Up => (Vec3::new(-4., 2., -0.5), false),
+        Direction::Down => (Vec3::new(4., -3., 0.5), false),
+        Direction::Left => (Vec3::new(-5., -2., 0.5), true),
+        Direction::Right => (Vec3::new(5., -2., 0.5), false),
+    };
+    transform.translation = offset;
+    sprite.flip_x = flip;
+}
@@ -0,0 +1,223 @@
+use bevy::prelude::*;
+
+use crate::{
+    health::{Health, Stamina},
+    hotbar::HOTBAR_SLOTS,
+    inventory::{ActiveItem, Inventory},
+    item_database::ItemDatabase,
+    player::Player,
+    AppState, FontAssets, SpriteAssets,
+};
+
+// Relative to the camera, which sees 320x240 of the world
+const HUD_ORIGIN: Vec2 = Vec2::new(-156., -108.);
+const HUD_ICONS: usize = 10;
+const Z_HUD: f32 = -1.;
+// Sprites in the ui_and_effects atlas
+const HEART_SPRITE: usize = 6;
+const BOLT_SPRITE: usize = 7;
+const EMPTY_COLOR: Color = Color::rgb(0.25, 0.25, 0.25);
+const HOTBAR_ORIGIN: Vec2 = Vec2::new(60., -108.);
+const HOTBAR_SPACING: f32 = 12.;
+const SELECT_LEFT_SPRITE: usize = 0;
+const SELECT_RIGHT_SPRITE: usize = 1;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(spawn_hud)
+                .with_system(update_hud)
+                .with_system(update_hotbar),
+        );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HudStat {
+    Health,
+    Stamina,
+}
+
+/// One heart or bolt, lit while the stat is above its index
+#[derive(Component)]
+struct HudIcon {
+    stat: HudStat,
+    index: i32,
+}
+
+/// The item sprite shown for one of the hotbar slots
+#[derive(Component)]
+struct HotbarIcon(usize);
+
+/// The brackets around the held slot
+#[derive(Component)]
+struct HotbarSelector;
+
+/// Name and amount of what is being held
+#[derive(Component)]
+struct HeldItemLabel;
+
+// The hud hangs off of the game camera so it stays put on screen,
+// it gets built as soon as that camera shows up
+fn spawn_hud(
+    mut commands: Commands,
+    cameras: Query<Entity, Added<Camera2d>>,
+    sprites: Res<SpriteAssets>,
+    font: Res<FontAssets>,
+) {
+    for camera in cameras.iter() {
+        commands.entity(camera).with_children(|parent| {
+            for (row, stat, sprite) in [
+                (0., HudStat::Health, HEART_SPRITE),
+                (1., HudStat::Stamina, BOLT_SPRITE),
+            ] {
+                for i in 0..HUD_ICONS {
+                    let pos = HUD_ORIGIN + Vec2::new(i as f32 * 8., row * -8.);
+                    parent.spawn((
+                        SpriteSheetBundle {
+                            texture_atlas: sprites.ui_and_effects.clone(),
+                            sprite: TextureAtlasSprite::new(sprite),
+                            transform: Transform::from_xyz(pos.x, pos.y, Z_HUD),
+                            ..default()
+                        },
+                        HudIcon {
+                            stat,
+                            index: i as i32,
+                        },
+                    ));
+                }
+            }
+
+            for i in 0..HOTBAR_SLOTS {
+                let pos = HOTBAR_ORIGIN + Vec2::new(i as f32 * HOTBAR_SPACING, 0.);
+                parent.spawn((
+                    SpriteSheetBundle {
+                        texture_atlas: sprites.items.clone(),
+                        transform: Transform::from_xyz(pos.x, pos.y, Z_HUD),
+                        ..default()
+                    },
+                    HotbarIcon(i),
+                ));
+            }
+
+            parent
+                .spawn((
+                    SpatialBundle::from_transform(Transform::from_xyz(
+                        HOTBAR_ORIGIN.x,
+                        HOTBAR_ORIGIN.y,
+                        Z_HUD,
+                    )),
+                    HotbarSelector,
+                ))
+                .with_children(|selector| {
+                    for (x, sprite) in [(-7., SELECT_LEFT_SPRITE), (7., SELECT_RIGHT_SPRITE)] {
+                        selector.spawn(SpriteSheetBundle {
+                            texture_atlas: sprites.ui_and_effects.clone(),
+                            sprite: TextureAtlasSprite::new(sprite),
+                            transform: Transform::from_xyz(x, 0., 0.),
+                            ..default()
+                        });
+                    }
+                });
+
+            // drawn at double size and scaled down so the pixel font stays sharp
+            let label_pos =
+                HOTBAR_ORIGIN + Vec2::new((HOTBAR_SLOTS - 1) as f32 * HOTBAR_SPACING + 4., 10.);
+            parent.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        String::new(),
+                        TextStyle {
+                            font: font.monogram.clone(),
+                            font_size: 16.0,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_alignment(TextAlignment::CENTER_RIGHT),
+                    transform: Transform::from_xyz(label_pos.x, label_pos.y, Z_HUD)
+                        .with_scale(Vec3::splat(0.5)),
+                    ..default()
+                },
+                HeldItemLabel,
+            ));
+        });
+    }
+}
+
+fn update_hud(
+    player_q: Query<(&Health, &Stamina), With<Player>>,
+    mut icons: Query<(&HudIcon, &mut TextureAtlasSprite, &mut Visibility)>,
+) {
+    let (health, stamina) = match player_q.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    for (icon, mut sprite, mut visibility) in icons.iter_mut() {
+        let (current, max, lit) = match icon.stat {
+            HudStat::Health => (health.current, health.max, Color::rgb(0.9, 0.1, 0.1)),
+            HudStat::Stamina => (stamina.current, stamina.max, Color::rgb(0.95, 0.85, 0.1)),
+        };
+        visibility.is_visible = icon.index < max;
+        sprite.color = if icon.index < current {
+            lit
+        } else {
+            EMPTY_COLOR
+        };
+    }
+}
+
+type HotbarIconQuery<'a> = (
+    &'a HotbarIcon,
+    &'a mut TextureAtlasSprite,
+    &'a mut Visibility,
+);
+fn update_hotbar(
+    player_q: Query<(&Inventory, &ActiveItem), With<Player>>,
+    mut icons: Query<HotbarIconQuery>,
+    mut selector: Query<
+        (&mut Transform, &mut Visibility),
+        (With<HotbarSelector>, Without<HotbarIcon>),
+    >,
+    mut label: Query<&mut Text, With<HeldItemLabel>>,
+    item_db: Res<ItemDatabase>,
+) {
+    let (inventory, active) = match player_q.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    for (icon, mut sprite, mut visibility) in icons.iter_mut() {
+        match inventory
+            .items
+            .get(icon.0)
+            .and_then(|item| item_db.get(item.id))
+        {
+            Some(def) => {
+                visibility.is_visible = true;
+                sprite.index = def.sprite_index;
+                sprite.color = def.color();
+            }
+            None => visibility.is_visible = false,
+        }
+    }
+
+    // the inventory list can pick slots past the end of the hotbar
+    for (mut transform, mut visibility) in selector.iter_mut() {
+        visibility.is_visible = active.slot < HOTBAR_SLOTS;
+        transform.translation.x = HOTBAR_ORIGIN.x + active.slot as f32 * HOTBAR_SPACING;
+    }
+
+    for mut text in label.iter_mut() {
+        text.sections[0].value = match &active.item {
+            Some(item) if item.amt > 1 => {
+                format!("{} x{}", item_db.display_name(item.id), item.amt)
+            }
+            Some(item) => item_db.display_name(item.id).to_string(),
+            None => String::new(),
+        };
+    }
+}
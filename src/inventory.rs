@@ -2,13 +2,24 @@ use bevy::{prelude::*, ui::widget::ImageMode};
 use bevy_inspector_egui::{Inspectable, RegisterInspectable};
 
 use crate::{
-    item::Item,
-    player::{Interact, Player, PlayerEntity},
-    AppState, FontAssets, SpriteAssets,
+    controls::{Action, Actions},
+    item::{spawn_dropped_item, Item, ItemIcons},
+    item_database::{ItemDatabase, ItemId, Tool, ToolKind},
+    map_generation::TILE_SIZE,
+    player::{Direction, Interact, PSystems, Player, PlayerEntity},
+    AppState, FontAssets, SessionEntity, SpriteAssets,
 };
 
-const MAX_ITEM_STACK: u32 = 999;
 const Z_UI: f32 = 80.;
+const GRID_COLUMNS: usize = 4;
+const GRID_ROWS: usize = 4;
+const GRID_LEFT: f32 = 28.;
+const GRID_TOP: f32 = 48.;
+const SLOT_SIZE: f32 = 40.;
+const SLOT_SPACING: f32 = 44.;
+const SLOT_COLOR: Color = Color::rgba(0.2, 0.2, 0.2, 0.6);
+const SELECTED_SLOT_COLOR: Color = Color::rgba(0.9, 0.75, 0.2, 0.8);
+const TOOLTIP_LINES: usize = 3;
 
 pub struct InventoryPlugin;
 
@@ -24,9 +35,20 @@ impl Plugin for InventoryPlugin {
                         .label(Interact::Reciever)
                         .after(Interact::Caller),
                 )
-                .with_system(ui_inventory_update)
-                .with_system(toggle_ui_menu),
+                .with_system(ui_inventory_update.after(ActiveItemSystems::Update))
+                .with_system(ui_inventory_tooltip.after(ActiveItemSystems::Update))
+                .with_system(toggle_ui_menu)
+                .with_system(inventory_cursor_input.label(ActiveItemSystems::Select))
+                .with_system(inventory_stack_input.before(ActiveItemSystems::Update))
+                .with_system(
+                    update_active_item
+                        .label(ActiveItemSystems::Update)
+                        .after(ActiveItemSystems::Select)
+                        .before(PSystems::Attack),
+                )
+                .with_system(drop_item.after(ActiveItemSystems::Update)),
         )
+        .init_resource::<InventoryScroll>()
         .add_event::<ItemPickup>()
         .add_event::<PlayerPickupSuccess>()
         .add_event::<InventoryUpdate>()
@@ -34,23 +56,68 @@ impl Plugin for InventoryPlugin {
     }
 }
 
-#[derive(Component, Default, Inspectable)]
+#[derive(Component, Default, Inspectable, Clone)]
 pub struct Inventory {
     pub items: Vec<Item>,
     pub capacity: i32,
 }
 
 #[derive(Component)]
-pub struct Stackable;
+pub struct PlayerMenu;
 
+/// A ui window that takes over the movement and action keys while it is open
 #[derive(Component)]
-pub struct PlayerMenu;
+pub struct MenuWindow;
 
 #[derive(Component)]
 pub struct InventoryUINode;
 
+/// One square of the grid, numbered by where it is on screen rather than by item
+#[derive(Component)]
+struct InventorySlot(usize);
+
 #[derive(Component)]
-pub struct InventorySlot(i32);
+struct SlotIcon(usize);
+
+#[derive(Component)]
+struct SlotCount(usize);
+
+#[derive(Component)]
+enum ScrollHint {
+    Up,
+    Down,
+}
+
+#[derive(Component)]
+struct TooltipLine(usize);
+
+/// The first row of the inventory that is on screen
+#[derive(Resource, Default)]
+struct InventoryScroll(usize);
+
+/// The inventory slot in the player's hand, it's what gets swung, placed and dropped
+#[derive(Component, Default)]
+pub struct ActiveItem {
+    pub slot: usize,
+    /// A copy of what is in that slot, kept up to date every frame
+    pub item: Option<Item>,
+}
+
+impl ActiveItem {
+    /// The tool being held if it's the kind the job needs
+    pub fn tool(&self, item_db: &ItemDatabase, kind: ToolKind) -> Option<Tool> {
+        self.item
+            .as_ref()
+            .and_then(|item| item_db.tool(item.id))
+            .filter(|tool| tool.kind == kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub enum ActiveItemSystems {
+    Select,
+    Update,
+}
 
 impl Inventory {
     pub fn new(capacity: i32) -> Self {
@@ -59,28 +126,259 @@ impl Inventory {
             capacity,
         }
     }
+
+    /// Total amount of an item across every stack of it
+    pub fn count_of(&self, id: ItemId) -> u32 {
+        self.items
+            .iter()
+            .filter(|item| item.id == id)
+            .map(|item| item.amt)
+            .sum()
+    }
+
+    /// Takes amt of the item out, emptying the newest stacks first.
+    /// Nothing is removed if there isn't enough of it.
+    pub fn remove(&mut self, id: ItemId, amt: u32) -> bool {
+        if self.count_of(id) < amt {
+            return false;
+        }
+
+        let mut left = amt;
+        for item in self.items.iter_mut().rev().filter(|item| item.id == id) {
+            let taken = left.min(item.amt);
+            item.amt -= taken;
+            left -= taken;
+            if left == 0 {
+                break;
+            }
+        }
+        self.items.retain(|item| item.amt > 0);
+        true
+    }
+
+    /// Takes up to amt out of the stack in a slot, the slot is freed once it's empty
+    pub fn take_from_slot(&mut self, slot: usize, amt: u32) -> Option<Item> {
+        let stack = self.items.get_mut(slot)?;
+        let taken = Item {
+            id: stack.id,
+            amt: amt.min(stack.amt),
+        };
+        stack.amt -= taken.amt;
+        if stack.amt == 0 {
+            self.items.remove(slot);
+        }
+        Some(taken)
+    }
+
+    /// How much more of an item there is space for, in its stacks and the free slots
+    pub fn room_for(&self, id: ItemId, item_db: &ItemDatabase) -> u32 {
+        let max_stack = item_db.max_stack(id);
+        let free_slots = (self.capacity - self.items.len() as i32).max(0) as u32;
+        let in_stacks: u32 = self
+            .items
+            .iter()
+            .filter(|stack| stack.id == id)
+            .map(|stack| max_stack.saturating_sub(stack.amt))
+            .sum();
+        in_stacks.saturating_add(free_slots.saturating_mul(max_stack))
+    }
+
+    /// Puts in as much of the item as fits, topping up existing stacks before taking
+    /// new slots at the end so the hotbar doesn't shuffle. Whatever didn't fit is handed back.
+    pub fn try_add(&mut self, mut item: Item, item_db: &ItemDatabase) -> Option<Item> {
+        let max_stack = item_db.max_stack(item.id);
+        for stack in self.items.iter_mut().filter(|stack| stack.id == item.id) {
+            let moved = item.amt.min(max_stack.saturating_sub(stack.amt));
+            stack.amt += moved;
+            item.amt -= moved;
+        }
+
+        while item.amt > 0 && (self.items.len() as i32) < self.capacity {
+            let moved = item.amt.min(max_stack);
+            self.items.push(Item {
+                id: item.id,
+                amt: moved,
+            });
+            item.amt -= moved;
+        }
+
+        if item.amt == 0 {
+            None
+        } else {
+            Some(item)
+        }
+    }
+
+    /// Puts the whole item in, nothing is added if it doesn't all fit
+    pub fn add(&mut self, item: Item, item_db: &ItemDatabase) -> bool {
+        if self.room_for(item.id, item_db) < item.amt {
+            return false;
+        }
+        self.try_add(item, item_db);
+        true
+    }
+
+    /// Moves amt off of the stack in a slot into a new slot right after it
+    pub fn split_stack(&mut self, slot: usize, amt: u32) -> bool {
+        if (self.items.len() as i32) >= self.capacity {
+            return false;
+        }
+        let stack = match self.items.get_mut(slot) {
+            Some(stack) if amt > 0 && amt < stack.amt => stack,
+            _ => return false,
+        };
+
+        stack.amt -= amt;
+        let split = Item { id: stack.id, amt };
+        self.items.insert(slot + 1, split);
+        true
+    }
+
+    /// Folds partly filled stacks of the same item together to free up slots
+    pub fn merge(&mut self, item_db: &ItemDatabase) {
+        for item in std::mem::take(&mut self.items) {
+            let max_stack = item_db.max_stack(item.id);
+            let mut left = item.amt;
+            for stack in self.items.iter_mut().filter(|stack| stack.id == item.id) {
+                let moved = left.min(max_stack.saturating_sub(stack.amt));
+                stack.amt += moved;
+                left -= moved;
+            }
+            if left > 0 {
+                self.items.push(Item {
+                    id: item.id,
+                    amt: left,
+                });
+            }
+        }
+    }
 }
 
+// Opens and closes every menu that belongs to the player together
 fn toggle_ui_menu(
-    keeb_input: Res<Input<KeyCode>>,
-    mut query: Query<(&mut Visibility, &mut Transform), With<InventoryUINode>>,
+    actions: Res<Actions>,
+    mut query: Query<&mut Visibility, With<PlayerMenu>>,
     mut ev_invopen: EventWriter<InventoryUpdate>,
 ) {
-    let mut menu = query.single_mut();
-
-    if keeb_input.just_pressed(KeyCode::X) {
-        menu.0.is_visible = !menu.0.is_visible;
+    let mut is_open = false;
+    for mut menu in query.iter_mut() {
+        if actions.just_pressed(Action::OpenInventory) {
+            menu.is_visible = !menu.is_visible;
+        }
+        is_open |= menu.is_visible;
     }
 
-    if menu.0.is_visible {
+    if is_open {
         ev_invopen.send(InventoryUpdate);
     }
 }
 
+// The movement actions walk the cursor around the grid while the inventory is
+// open, whatever it lands on is what gets held
+fn inventory_cursor_input(
+    actions: Res<Actions>,
+    menu: Query<&Visibility, With<InventoryUINode>>,
+    mut player_q: Query<(&Inventory, &mut ActiveItem), With<Player>>,
+) {
+    let (inventory, mut active) = player_q.single_mut();
+    if !menu.single().is_visible {
+        return;
+    }
+
+    let last = (inventory.capacity.max(1) - 1) as usize;
+    let slot = active.slot.min(last);
+
+    let moved = if actions.just_pressed(Action::MoveUp) {
+        slot.saturating_sub(GRID_COLUMNS)
+    } else if actions.just_pressed(Action::MoveDown) {
+        (slot + GRID_COLUMNS).min(last)
+    } else if actions.just_pressed(Action::MoveLeft) {
+        slot.saturating_sub(1)
+    } else if actions.just_pressed(Action::MoveRight) {
+        (slot + 1).min(last)
+    } else {
+        return;
+    };
+    if moved != active.slot {
+        active.slot = moved;
+    }
+}
+
+// H splits the held stack in half, M folds partial stacks back together
+fn inventory_stack_input(
+    actions: Res<Actions>,
+    menu: Query<&Visibility, With<InventoryUINode>>,
+    mut player_q: Query<(&mut Inventory, &ActiveItem), With<Player>>,
+    item_db: Res<ItemDatabase>,
+    mut ev_inventory_update: EventWriter<InventoryUpdate>,
+) {
+    if !menu.single().is_visible {
+        return;
+    }
+
+    let (mut inventory, active) = player_q.single_mut();
+    if actions.just_pressed(Action::SplitStack) {
+        let half = inventory
+            .items
+            .get(active.slot)
+            .map_or(0, |item| item.amt / 2);
+        if inventory.split_stack(active.slot, half) {
+            ev_inventory_update.send(InventoryUpdate);
+        }
+    }
+    if actions.just_pressed(Action::MergeStacks) {
+        inventory.merge(&item_db);
+        ev_inventory_update.send(InventoryUpdate);
+    }
+}
+
+type ActiveItemChanged = Or<(Changed<Inventory>, Changed<ActiveItem>)>;
+fn update_active_item(mut player_q: Query<(&Inventory, &mut ActiveItem), ActiveItemChanged>) {
+    // only written when it differs so this doesn't keep flagging itself as changed
+    for (inventory, mut active) in player_q.iter_mut() {
+        let item = inventory.items.get(active.slot).cloned();
+        if active.item.as_ref().map(|held| (held.id, held.amt))
+            != item.as_ref().map(|item| (item.id, item.amt))
+        {
+            active.item = item;
+        }
+    }
+}
+
+// Drops one of the held item in front of the player, holding the stack modifier
+// drops all of it
+fn drop_item(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    mut player_q: Query<(&Transform, &Direction, &ActiveItem, &mut Inventory), With<Player>>,
+    item_db: Res<ItemDatabase>,
+    sprites: Res<SpriteAssets>,
+    mut ev_inventory_update: EventWriter<InventoryUpdate>,
+) {
+    if !actions.just_pressed(Action::Drop) {
+        return;
+    }
+
+    let (transform, direction, active, mut inventory) = player_q.single_mut();
+    let amt = if actions.pressed(Action::WholeStack) {
+        u32::MAX
+    } else {
+        1
+    };
+    let dropped = match inventory.take_from_slot(active.slot, amt) {
+        Some(item) => item,
+        None => return,
+    };
+
+    let pos = transform.translation.truncate() + direction.offset().as_vec2() * TILE_SIZE;
+    spawn_dropped_item(&mut commands, &sprites, &item_db, dropped, pos);
+    ev_inventory_update.send(InventoryUpdate);
+}
+
 //Events
 pub struct ItemPickup {
     pub item: Entity,
-    pub what_item: String,
+    pub what_item: ItemId,
     pub who: Entity,
 }
 
@@ -88,56 +386,54 @@ pub struct PlayerPickupSuccess;
 
 pub struct InventoryUpdate;
 
-//Called when an entity pickups an item (only works for player)
+// Puts picked up items into whoever grabbed them, whatever doesn't fit stays on the ground
+#[allow(clippy::too_many_arguments)]
 fn add_to_inventory(
     mut ev_itempickup: EventReader<ItemPickup>,
     mut ev_success: EventWriter<PlayerPickupSuccess>,
+    mut ev_inventory_update: EventWriter<InventoryUpdate>,
     mut commands: Commands,
-    mut inventories: Query<&mut Inventory>, //Every inventory
-    all_items: Query<(&Item, Option<&Stackable>)>, //Every item
+    mut inventories: Query<&mut Inventory>,
+    mut ground_items: Query<&mut Item>,
+    item_db: Res<ItemDatabase>,
     player_e: Res<PlayerEntity>,
 ) {
+    let mut picked_up = false;
     for ev in ev_itempickup.iter() {
-        let mut ev_inventory = match inventories.get_mut(ev.who) {
-            Ok(inv) => {
-                if (inv.items.len() as i32) < inv.capacity {
-                    inv
-                } else {
-                    warn!("Inventory is full, cannot perform action");
-                    return;
-                }
-            }
+        let mut inventory = match inventories.get_mut(ev.who) {
+            Ok(inventory) => inventory,
             Err(err) => {
                 error!("There is no inventory on that entity err msg: {}", err);
-                return;
+                continue;
             }
         };
-        match all_items.get(ev.item) {
-            //make sure the ground item still exists and an old event didnt already handle it
-            Ok((ground_item, is_stackable)) => {
-                if is_stackable.is_some() && ev_inventory.items.contains(ground_item) {
-                    if let Some(mut item_in_inv) = ev_inventory
-                        .items
-                        .iter_mut()
-                        .find(|item| *item == ground_item)
-                    {
-                        if item_in_inv.amt >= MAX_ITEM_STACK {
-                            continue;
-                        }
-                        item_in_inv.amt += ground_item.amt;
-                    }
-                } else {
-                    //If the item doesn't exist in the inventory or it does but it is not stackable
-                    ev_inventory.items.insert(0, ground_item.clone());
-                }
-
-                if ev.who == player_e.0 {
-                    ev_success.send(PlayerPickupSuccess);
-                }
+        // make sure the ground item still exists and an earlier event didn't already take it
+        let mut ground_item = match ground_items.get_mut(ev.item) {
+            Ok(item) if item.amt > 0 => item,
+            _ => continue,
+        };
+
+        match inventory.try_add(ground_item.clone(), &item_db) {
+            None => {
+                ground_item.amt = 0;
                 commands.entity(ev.item).despawn();
             }
-            Err(err) => eprintln!("{}, id:{}", err, ev.item.index()),
-        };
+            Some(rest) if rest.amt < ground_item.amt => ground_item.amt = rest.amt,
+            Some(_) => {
+                warn!("Inventory is full, cannot perform action");
+                continue;
+            }
+        }
+
+        if ev.who == player_e.0 {
+            ev_success.send(PlayerPickupSuccess);
+            picked_up = true;
+        }
+    }
+
+    // one refresh for the ui no matter how many things were grabbed this frame
+    if picked_up {
+        ev_inventory_update.send(InventoryUpdate);
     }
 }
 
@@ -151,16 +447,30 @@ fn inventory_ui_startup(
         font_size: 24.0,
         color: Color::BLACK,
     };
+    let count_style = TextStyle {
+        font: font.monogram.clone(),
+        font_size: 16.0,
+        color: Color::WHITE,
+    };
 
     let inv_bg_style = Style {
         align_self: AlignSelf::Center,
         position_type: PositionType::Absolute,
         position: UiRect {
             top: Val::Percent(5.),
-            left: Val::Percent(15.),
+            left: Val::Percent(2.),
+            ..default()
+        },
+        size: Size::new(Val::Px(240.), Val::Px(336.)),
+        ..default()
+    };
+    let at = |left: f32, top: f32| Style {
+        position_type: PositionType::Absolute,
+        position: UiRect {
+            left: Val::Px(left),
+            top: Val::Px(top),
             ..default()
         },
-        size: Size::new(Val::Px(400.), Val::Px(700.)),
         ..default()
     };
 
@@ -172,6 +482,9 @@ fn inventory_ui_startup(
                 ..default()
             },
             InventoryUINode,
+            PlayerMenu,
+            MenuWindow,
+            SessionEntity,
         ))
         .with_children(|parent| {
             // the window which objects for the inventory ui will sit on
@@ -183,49 +496,330 @@ fn inventory_ui_startup(
                     ..default()
                 })
                 .with_children(|inv_parent| {
-                    // multiple text objects for items
-                    for i in 0..20 {
-                        let offset: f32 = i as f32 * 20.0;
+                    inv_parent.spawn(
+                        TextBundle::from_section("Inventory", text_style.clone())
+                            .with_style(at(GRID_LEFT, 16.)),
+                    );
+
+                    // the grid only has nodes for the rows on screen, scrolling
+                    // changes which items they show
+                    for i in 0..GRID_COLUMNS * GRID_ROWS {
+                        let (column, row) = ((i % GRID_COLUMNS) as f32, (i / GRID_COLUMNS) as f32);
                         inv_parent
-                            .spawn(
-                                TextBundle::from_section(String::new(), text_style.clone())
-                                    .with_style(Style {
-                                        align_self: AlignSelf::Center,
-                                        position_type: PositionType::Absolute,
-                                        position: UiRect {
-                                            top: Val::Px(12. + offset),
-                                            left: Val::Px(44.),
+                            .spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(SLOT_SIZE), Val::Px(SLOT_SIZE)),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..at(
+                                            GRID_LEFT + column * SLOT_SPACING,
+                                            GRID_TOP + row * SLOT_SPACING,
+                                        )
+                                    },
+                                    ..default()
+                                },
+                                InventorySlot(i),
+                            ))
+                            .with_children(|slot| {
+                                slot.spawn((
+                                    ImageBundle {
+                                        style: Style {
+                                            size: Size::new(Val::Px(24.), Val::Px(24.)),
                                             ..default()
                                         },
                                         ..default()
-                                    }),
-                            )
-                            .insert(InventorySlot(i));
+                                    },
+                                    SlotIcon(i),
+                                ));
+                                slot.spawn((
+                                    TextBundle::from_section(String::new(), count_style.clone())
+                                        .with_style(Style {
+                                            position_type: PositionType::Absolute,
+                                            position: UiRect {
+                                                right: Val::Px(2.),
+                                                bottom: Val::Px(0.),
+                                                ..default()
+                                            },
+                                            ..default()
+                                        }),
+                                    SlotCount(i),
+                                ));
+                            });
+                    }
+
+                    let hint_left = GRID_LEFT + GRID_COLUMNS as f32 * SLOT_SPACING;
+                    let last_row = GRID_TOP + (GRID_ROWS - 1) as f32 * SLOT_SPACING;
+                    for (hint, top) in [(ScrollHint::Up, GRID_TOP), (ScrollHint::Down, last_row)] {
+                        let arrow = match hint {
+                            ScrollHint::Up => "^",
+                            ScrollHint::Down => "v",
+                        };
+                        inv_parent.spawn((
+                            TextBundle::from_section(arrow, text_style.clone())
+                                .with_style(at(hint_left, top)),
+                            hint,
+                        ));
+                    }
+
+                    let tooltip_top = GRID_TOP + GRID_ROWS as f32 * SLOT_SPACING + 8.;
+                    for line in 0..TOOLTIP_LINES {
+                        inv_parent.spawn((
+                            TextBundle::from_section(String::new(), text_style.clone())
+                                .with_style(at(GRID_LEFT, tooltip_top + line as f32 * 20.)),
+                            TooltipLine(line),
+                        ));
                     }
                 });
         });
 }
 
+type SlotIconQuery<'a> = (
+    &'a SlotIcon,
+    &'a mut UiImage,
+    &'a mut BackgroundColor,
+    &'a mut Visibility,
+);
+type ScrollHintQuery<'a> = (&'a ScrollHint, &'a mut Visibility);
+// Redrawn every frame while the window is open so it never shows anything stale
+#[allow(clippy::too_many_arguments)]
 fn ui_inventory_update(
-    mut ev_invopen: EventReader<InventoryUpdate>,
-    mut q_ui_slots: Query<(&mut Text, &InventorySlot)>,
-    q_inv: Query<(&mut Inventory, &Player)>, //Every inventory
-    player_e: Res<PlayerEntity>,
+    menu: Query<&Visibility, With<InventoryUINode>>,
+    player_q: Query<(&Inventory, &ActiveItem), With<Player>>,
+    mut scroll: ResMut<InventoryScroll>,
+    mut slots: Query<(&InventorySlot, &mut BackgroundColor)>,
+    mut icons: Query<SlotIconQuery, (Without<InventorySlot>, Without<InventoryUINode>)>,
+    mut counts: Query<(&SlotCount, &mut Text)>,
+    mut hints: Query<ScrollHintQuery, (Without<SlotIcon>, Without<InventoryUINode>)>,
+    item_db: Res<ItemDatabase>,
+    item_icons: Res<ItemIcons>,
 ) {
-    for _ in ev_invopen.iter() {
-        let (player_inv, _player) = match q_inv.get(player_e.0) {
-            Ok((inv, p)) => (inv, p),
-            Err(_) => panic!("Could not fetch the player's inventory!!!"),
+    if !menu.single().is_visible {
+        return;
+    }
+    let (inventory, active) = player_q.single();
+
+    // keep the held slot's row on screen
+    let active_row = active.slot / GRID_COLUMNS;
+    if active_row < scroll.0 {
+        scroll.0 = active_row;
+    } else if active_row >= scroll.0 + GRID_ROWS {
+        scroll.0 = active_row + 1 - GRID_ROWS;
+    }
+    let first = scroll.0 * GRID_COLUMNS;
+    let capacity = inventory.capacity.max(0) as usize;
+
+    for (slot, mut color) in slots.iter_mut() {
+        let index = first + slot.0;
+        color.0 = if index >= capacity {
+            Color::NONE
+        } else if index == active.slot {
+            SELECTED_SLOT_COLOR
+        } else {
+            SLOT_COLOR
         };
+    }
 
-        for (mut text, slot_idx) in q_ui_slots.iter_mut() {
-            let item = player_inv.items.get(slot_idx.0 as usize);
-            if item != None {
-                let i = item.unwrap().clone();
-                text.sections[0].value = format!("{: <20}AMT:{:>3}", i.name, i.amt);
-            } else {
-                text.sections[0].value = String::from("------");
+    for (icon, mut image, mut color, mut visibility) in icons.iter_mut() {
+        match inventory
+            .items
+            .get(first + icon.0)
+            .and_then(|item| item_db.get(item.id))
+        {
+            Some(def) => {
+                visibility.is_visible = true;
+                image.0 = item_icons.get(def.sprite_index);
+                color.0 = def.color();
             }
+            None => visibility.is_visible = false,
+        }
+    }
+
+    for (count, mut text) in counts.iter_mut() {
+        text.sections[0].value = match inventory.items.get(first + count.0) {
+            Some(item) if item.amt > 1 => item.amt.to_string(),
+            _ => String::new(),
+        };
+    }
+
+    let rows = (capacity + GRID_COLUMNS - 1) / GRID_COLUMNS;
+    for (hint, mut visibility) in hints.iter_mut() {
+        visibility.is_visible = match hint {
+            ScrollHint::Up => scroll.0 > 0,
+            ScrollHint::Down => scroll.0 + GRID_ROWS < rows,
+        };
+    }
+}
+
+// Describes whatever the cursor is on underneath the grid
+fn ui_inventory_tooltip(
+    menu: Query<&Visibility, With<InventoryUINode>>,
+    player_q: Query<&ActiveItem, With<Player>>,
+    mut lines: Query<(&TooltipLine, &mut Text)>,
+    item_db: Res<ItemDatabase>,
+) {
+    if !menu.single().is_visible {
+        return;
+    }
+
+    let active = player_q.single();
+    let def = active.item.as_ref().and_then(|item| item_db.get(item.id));
+    let tooltip: [String; TOOLTIP_LINES] = match (&active.item, def) {
+        (Some(item), Some(def)) => {
+            let details = match def.tool {
+                Some(tool) => format!("{:?} power {}", tool.kind, tool.power),
+                None => def.tags.join(", "),
+            };
+            [
+                def.display_name.clone(),
+                format!("Amount {}/{}", item.amt, def.max_stack),
+                details,
+            ]
         }
+        _ => Default::default(),
+    };
+
+    for (line, mut text) in lines.iter_mut() {
+        text.sections[0].value = tooltip[line.0].clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item_database::ItemDef;
+
+    const WOOD: ItemId = 0;
+    const PICKAXE: ItemId = 1;
+
+    fn item_db() -> ItemDatabase {
+        let def = |id, name: &str, max_stack| ItemDef {
+            id,
+            name: name.to_string(),
+            display_name: name.to_string(),
+            sprite_index: 0,
+            color: (1., 1., 1.),
+            max_stack,
+            tags: vec![],
+            tool: None,
+            light: None,
+            storage: None,
+        };
+        ItemDatabase::new(vec![def(WOOD, "wood", 10), def(PICKAXE, "pickaxe", 1)])
+    }
+
+    fn inventory(capacity: i32, stacks: &[(ItemId, u32)]) -> Inventory {
+        Inventory {
+            items: stacks.iter().map(|&(id, amt)| Item { id, amt }).collect(),
+            capacity,
+        }
+    }
+
+    // Item's == only looks at the id, so compare the amounts too
+    fn stacks(inventory: &Inventory) -> Vec<(ItemId, u32)> {
+        inventory
+            .items
+            .iter()
+            .map(|item| (item.id, item.amt))
+            .collect()
+    }
+
+    #[test]
+    fn room_for_counts_stacks_and_free_slots() {
+        let item_db = item_db();
+        let inventory = inventory(4, &[(WOOD, 7), (PICKAXE, 1), (WOOD, 10)]);
+        assert_eq!(inventory.room_for(WOOD, &item_db), 3 + 10);
+        assert_eq!(inventory.room_for(PICKAXE, &item_db), 1);
+
+        let full = inventory(2, &[(WOOD, 10), (PICKAXE, 1)]);
+        assert_eq!(full.room_for(WOOD, &item_db), 0);
+    }
+
+    #[test]
+    fn try_add_overflows_into_new_slots() {
+        let item_db = item_db();
+        let mut inventory = inventory(4, &[(WOOD, 7), (PICKAXE, 1)]);
+        let left = inventory.try_add(Item { id: WOOD, amt: 16 }, &item_db);
+        assert!(left.is_none());
+        assert_eq!(
+            stacks(&inventory),
+            vec![(WOOD, 10), (PICKAXE, 1), (WOOD, 10), (WOOD, 3)]
+        );
+    }
+
+    #[test]
+    fn try_add_hands_back_what_does_not_fit() {
+        let item_db = item_db();
+        let mut inventory = inventory(2, &[(WOOD, 7)]);
+        let left = inventory.try_add(Item { id: WOOD, amt: 20 }, &item_db);
+        assert_eq!(left.map(|item| item.amt), Some(7));
+        assert_eq!(stacks(&inventory), vec![(WOOD, 10), (WOOD, 10)]);
+
+        let left = inventory.try_add(
+            Item {
+                id: PICKAXE,
+                amt: 1,
+            },
+            &item_db,
+        );
+        assert_eq!(left.map(|item| (item.id, item.amt)), Some((PICKAXE, 1)));
+        assert_eq!(stacks(&inventory), vec![(WOOD, 10), (WOOD, 10)]);
+    }
+
+    #[test]
+    fn add_is_all_or_nothing() {
+        let item_db = item_db();
+        let mut inventory = inventory(2, &[(WOOD, 7)]);
+        assert!(!inventory.add(Item { id: WOOD, amt: 14 }, &item_db));
+        assert_eq!(stacks(&inventory), vec![(WOOD, 7)]);
+        assert!(inventory.add(Item { id: WOOD, amt: 13 }, &item_db));
+        assert_eq!(stacks(&inventory), vec![(WOOD, 10), (WOOD, 10)]);
+    }
+
+    #[test]
+    fn split_stack_needs_less_than_the_stack_and_a_free_slot() {
+        let mut inventory = inventory(3, &[(WOOD, 7), (PICKAXE, 1)]);
+        assert!(!inventory.split_stack(0, 7));
+        assert!(!inventory.split_stack(0, 8));
+        assert!(!inventory.split_stack(0, 0));
+        assert!(!inventory.split_stack(5, 1));
+        assert_eq!(stacks(&inventory), vec![(WOOD, 7), (PICKAXE, 1)]);
+
+        assert!(inventory.split_stack(0, 3));
+        assert_eq!(stacks(&inventory), vec![(WOOD, 4), (WOOD, 3), (PICKAXE, 1)]);
+
+        // no slot left to put the split in
+        assert!(!inventory.split_stack(0, 1));
+        assert_eq!(stacks(&inventory), vec![(WOOD, 4), (WOOD, 3), (PICKAXE, 1)]);
+    }
+
+    #[test]
+    fn merge_only_joins_the_same_item() {
+        let item_db = item_db();
+        let mut inventory = inventory(
+            6,
+            &[(WOOD, 4), (PICKAXE, 1), (WOOD, 8), (PICKAXE, 1), (WOOD, 5)],
+        );
+        inventory.merge(&item_db);
+        assert_eq!(
+            stacks(&inventory),
+            vec![(WOOD, 10), (PICKAXE, 1), (WOOD, 7), (PICKAXE, 1)]
+        );
+    }
+
+    #[test]
+    fn remove_takes_from_the_newest_stacks() {
+        let mut inventory = inventory(4, &[(WOOD, 10), (PICKAXE, 1), (WOOD, 4)]);
+        assert!(!inventory.remove(WOOD, 15));
+        assert_eq!(
+            stacks(&inventory),
+            vec![(WOOD, 10), (PICKAXE, 1), (WOOD, 4)]
+        );
+
+        assert!(inventory.remove(WOOD, 6));
+        assert_eq!(stacks(&inventory), vec![(WOOD, 8), (PICKAXE, 1)]);
+        assert!(inventory.remove(WOOD, 8));
+        assert_eq!(stacks(&inventory), vec![(PICKAXE, 1)]);
+        assert!(inventory.remove(WOOD, 0));
     }
 }
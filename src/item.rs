@@ -1,99 +1,212 @@
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
 use bevy_inspector_egui::{Inspectable, RegisterInspectable};
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::{inventory::Stackable, AppState, SpriteAssets};
+use crate::{
+    assetload::DataAssets,
+    item_database::{ItemDatabase, ItemDatabaseLoader, ItemId},
+    AppState, SessionEntity, SpriteAssets,
+};
 
 pub struct ItemPlugin;
 
 impl Plugin for ItemPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(AppState::InGame).with_system(spawn_test_items))
+        app.add_asset::<ItemDatabase>()
+            .init_asset_loader::<ItemDatabaseLoader>()
+            .add_system_set(
+                SystemSet::on_exit(AppState::AssetLoad)
+                    .with_system(insert_item_db)
+                    .with_system(build_item_icons),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame).with_system(tick_pickup_cooldowns),
+            )
             .register_inspectable::<Item>();
     }
 }
 
 const Z_ITEM: f32 = 35.;
+const PICKUP_RADIUS: f32 = 4.;
 
-#[derive(Component, Inspectable, Clone)]
+#[derive(Component, Inspectable, Clone, Serialize, Deserialize)]
 pub struct Item {
-    pub name: String,
+    pub id: ItemId,
     pub amt: u32,
-    pub sprite_index: u32,
 }
 
 impl PartialEq for Item {
     fn eq(&self, other: &Self) -> bool {
-        self.name == other.name
+        self.id == other.id
     }
 }
 impl Eq for Item {}
 
+/// Keeps a dropped item from being picked straight back up, the item has no
+/// pickup sensor until the timer runs out
+#[derive(Component, Deref, DerefMut)]
+pub struct PickupCooldown(Timer);
+
+impl Default for PickupCooldown {
+    fn default() -> Self {
+        PickupCooldown(Timer::from_seconds(1.0, TimerMode::Once))
+    }
+}
+
+/// Every sprite of the items atlas cut out into its own image, ui nodes can't draw
+/// from a texture atlas
+#[derive(Resource)]
+pub struct ItemIcons(Vec<Handle<Image>>);
+
+impl ItemIcons {
+    pub fn get(&self, sprite_index: usize) -> Handle<Image> {
+        self.0.get(sprite_index).cloned().unwrap_or_default()
+    }
+}
+
 impl Default for Item {
     fn default() -> Self {
-        Item {
-            name: "Empty".to_string(),
-            amt: 0,
-            sprite_index: 0,
+        Item { id: 0, amt: 0 }
+    }
+}
+
+fn tick_pickup_cooldowns(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut cooldowns: Query<(Entity, &mut PickupCooldown)>,
+) {
+    for (entity, mut cooldown) in cooldowns.iter_mut() {
+        if cooldown.tick(time.delta()).finished() {
+            // a sensor that shows up already touching the player still starts a collision
+            commands
+                .entity(entity)
+                .remove::<PickupCooldown>()
+                .insert(Collider::ball(PICKUP_RADIUS));
         }
     }
 }
 
-fn spawn_test_items(mut commands: Commands, sprites: Res<SpriteAssets>) {
-    for i in 0..100 {
-        let offset_x: f32 = i as f32;
-        commands.spawn((
-            SpriteSheetBundle {
-                texture_atlas: sprites.items.clone(),
-                sprite: TextureAtlasSprite {
-                    index: 4,
-                    color: Color::MAROON,
-                    ..default()
+// The database is copied out of its asset so systems can just ask for Res<ItemDatabase>
+fn insert_item_db(
+    mut commands: Commands,
+    data: Res<DataAssets>,
+    item_dbs: Res<Assets<ItemDatabase>>,
+) {
+    let item_db = item_dbs
+        .get(&data.items)
+        .expect("The item database should be loaded by now");
+    commands.insert_resource(item_db.clone());
+}
+
+fn build_item_icons(
+    mut commands: Commands,
+    sprites: Res<SpriteAssets>,
+    atlases: Res<Assets<TextureAtlas>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let atlas = atlases
+        .get(&sprites.items)
+        .expect("The item atlas should be loaded by now");
+    let sheet = images
+        .get(&atlas.texture)
+        .expect("The item sheet should be loaded by now");
+    let sheet_width = sheet.texture_descriptor.size.width as usize;
+    // pngs are always loaded as 8 bit rgba
+    let pixel_size = 4;
+
+    let icons: Vec<Image> = atlas
+        .textures
+        .iter()
+        .map(|rect| {
+            let (x, y) = (rect.min.x as usize, rect.min.y as usize);
+            let (width, height) = (rect.width() as usize, rect.height() as usize);
+            let mut data = Vec::with_capacity(width * height * pixel_size);
+            for row in y..y + height {
+                let start = (row * sheet_width + x) * pixel_size;
+                data.extend_from_slice(&sheet.data[start..start + width * pixel_size]);
+            }
+            Image::new(
+                Extent3d {
+                    width: width as u32,
+                    height: height as u32,
+                    depth_or_array_layers: 1,
                 },
-                transform: Transform::from_xyz(30. + offset_x * 2.0, 5., Z_ITEM),
-                ..default()
-            },
-            Item {
-                name: format!("Wood, {}", i % 10),
-                amt: 1,
-                sprite_index: 4,
-            },
-            Stackable,
-        ));
-    }
+                TextureDimension::D2,
+                data,
+                TextureFormat::Rgba8UnormSrgb,
+            )
+        })
+        .collect();
+
+    commands.insert_resource(ItemIcons(
+        icons.into_iter().map(|icon| images.add(icon)).collect(),
+    ));
+}
+
+/// Puts an item in the world that can be picked up
+pub fn spawn_ground_item(
+    commands: &mut Commands,
+    sprites: &SpriteAssets,
+    item_db: &ItemDatabase,
+    item: Item,
+    pos: Vec2,
+) -> Entity {
+    let ground_item = spawn_item_entity(commands, sprites, item_db, item, pos);
+    commands
+        .entity(ground_item)
+        .insert(Collider::ball(PICKUP_RADIUS));
+    ground_item
+}
+
+/// Puts an item in the world that was thrown out, it can't be picked up for a moment
+pub fn spawn_dropped_item(
+    commands: &mut Commands,
+    sprites: &SpriteAssets,
+    item_db: &ItemDatabase,
+    item: Item,
+    pos: Vec2,
+) -> Entity {
+    let ground_item = spawn_item_entity(commands, sprites, item_db, item, pos);
+    commands
+        .entity(ground_item)
+        .insert(PickupCooldown::default());
+    ground_item
+}
+
+// Ground items are kinematic sensors, they don't bump into anything but the
+// player's collider still starts a collision with them
+fn spawn_item_entity(
+    commands: &mut Commands,
+    sprites: &SpriteAssets,
+    item_db: &ItemDatabase,
+    item: Item,
+    pos: Vec2,
+) -> Entity {
+    let (index, color) = match item_db.get(item.id) {
+        Some(def) => (def.sprite_index, def.color()),
+        None => (0, Color::FUCHSIA),
+    };
 
-    for i in (0..100).step_by(10) {
-        let offset_x: f32 = i as f32;
-        commands.spawn((
+    commands
+        .spawn((
             SpriteSheetBundle {
                 texture_atlas: sprites.items.clone(),
                 sprite: TextureAtlasSprite {
-                    index: 2,
-                    color: Color::GRAY,
+                    index,
+                    color,
                     ..default()
                 },
-                transform: Transform::from_xyz(30. + offset_x * 2.0, 20., Z_ITEM),
+                transform: Transform::from_xyz(pos.x, pos.y, Z_ITEM),
                 ..default()
             },
-            Item::pebble(),
-            Stackable,
-        ));
-    }
-}
-
-impl Item {
-    fn pebble() -> Item {
-        Item {
-            name: "pebble".to_string(),
-            amt: 1,
-            sprite_index: 2,
-        }
-    }
-
-    /* fn wood() -> Item {
-        Item {
-            name: "Wood".to_string(),
-            amt: 1,
-            sprite_index: 4,
-        }
-    } */
+            item,
+            RigidBody::KinematicPositionBased,
+            Sensor,
+            SessionEntity,
+        ))
+        .id()
 }
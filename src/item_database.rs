@@ -0,0 +1,150 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::Deserialize;
+
+use crate::item::Item;
+
+pub type ItemId = u32;
+
+/// How an item looks and behaves, read from assets/data/items.ron
+#[derive(Deserialize, Clone)]
+pub struct ItemDef {
+    pub id: ItemId,
+    pub name: String,
+    pub display_name: String,
+    pub sprite_index: usize,
+    #[serde(default = "white")]
+    pub color: (f32, f32, f32),
+    #[serde(default = "one")]
+    pub max_stack: u32,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub tool: Option<Tool>,
+    /// How many tiles around it the item lights up when held or placed
+    #[serde(default)]
+    pub light: Option<f32>,
+    /// Slots it holds when placed as furniture, like a chest
+    #[serde(default)]
+    pub storage: Option<i32>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ToolKind {
+    Pickaxe,
+    Sword,
+    Axe,
+}
+
+/// What an item is good for hitting, higher power does more damage per hit
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct Tool {
+    pub kind: ToolKind,
+    pub power: u32,
+}
+
+fn white() -> (f32, f32, f32) {
+    (1., 1., 1.)
+}
+
+fn one() -> u32 {
+    1
+}
+
+impl ItemDef {
+    pub fn color(&self) -> Color {
+        Color::rgb(self.color.0, self.color.1, self.color.2)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+/// Every item definition, looked up by id or by name
+#[derive(TypeUuid, Resource, Clone, Default)]
+#[uuid = "6b1f6a7e-3c0c-4e55-9d43-2f1f9c0e8a51"]
+pub struct ItemDatabase {
+    defs: Vec<ItemDef>,
+    by_id: HashMap<ItemId, usize>,
+    by_name: HashMap<String, usize>,
+}
+
+impl ItemDatabase {
+    pub fn new(defs: Vec<ItemDef>) -> Self {
+        let mut by_id = HashMap::default();
+        let mut by_name = HashMap::default();
+        for (i, def) in defs.iter().enumerate() {
+            if by_id.insert(def.id, i).is_some() {
+                warn!("Item id {} is used more than once", def.id);
+            }
+            if by_name.insert(def.name.clone(), i).is_some() {
+                warn!("Item name {} is used more than once", def.name);
+            }
+        }
+
+        Self {
+            defs,
+            by_id,
+            by_name,
+        }
+    }
+
+    pub fn get(&self, id: ItemId) -> Option<&ItemDef> {
+        self.by_id.get(&id).map(|i| &self.defs[*i])
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&ItemDef> {
+        self.by_name.get(name).map(|i| &self.defs[*i])
+    }
+
+    /// An amount of the named item, panics if the item doesn't exist since that's a typo
+    /// in code or the data file
+    pub fn item(&self, name: &str, amt: u32) -> Item {
+        match self.by_name(name) {
+            Some(def) => Item { id: def.id, amt },
+            None => panic!("There is no item named {} in the item database", name),
+        }
+    }
+
+    pub fn display_name(&self, id: ItemId) -> &str {
+        self.get(id).map_or("???", |def| def.display_name.as_str())
+    }
+
+    pub fn max_stack(&self, id: ItemId) -> u32 {
+        self.get(id).map_or(1, |def| def.max_stack)
+    }
+
+    pub fn light(&self, id: ItemId) -> Option<f32> {
+        self.get(id).and_then(|def| def.light)
+    }
+
+    pub fn tool(&self, id: ItemId) -> Option<Tool> {
+        self.get(id).and_then(|def| def.tool)
+    }
+}
+
+#[derive(Default)]
+pub struct ItemDatabaseLoader;
+
+impl AssetLoader for ItemDatabaseLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let defs = ron::de::from_bytes::<Vec<ItemDef>>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(ItemDatabase::new(defs)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["items.ron"]
+    }
+}
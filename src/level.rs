@@ -0,0 +1,162 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    chest::Chest,
+    decoration::HarvestedDecorations,
+    furniture::Furniture,
+    health::Health,
+    inventory::Inventory,
+    item::Item,
+    item_database::ItemDatabase,
+    map_generation::{tile_to_world, world_to_tile, WorldEdits, WorldGenerator},
+    mob::Mob,
+    player::{PSystems, Player},
+    save::LevelSave,
+    tile_type::TileType,
+    AppState, SpriteAssets,
+};
+
+/*
+    The world is a stack of levels like in the original, the surface in the middle,
+    one level of sky above it and caves going down to a level of lava at the bottom.
+
+    Only the level the player is on exists as entities. Leaving a level packs its
+    changed tiles, items, furniture and mobs away the same way a save does, and they
+    are put back when the player returns.
+*/
+
+pub const SKY_LEVEL: i32 = 1;
+pub const SURFACE_LEVEL: i32 = 0;
+pub const DEEPEST_LEVEL: i32 = -3;
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentLevel>()
+            .init_resource::<StoredLevels>()
+            .add_event::<ChangeLevel>()
+            .add_event::<LevelChanged>()
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(use_stairs.after(PSystems::Movement))
+                    .with_system(change_level.label(LevelSystems::Change).after(use_stairs)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub enum LevelSystems {
+    /// The player is moved to another level
+    Change,
+}
+
+/// How far above or below the surface the player is
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CurrentLevel(pub i32);
+
+/// Every visited level the player isn't on right now
+#[derive(Resource, Default)]
+pub struct StoredLevels(pub HashMap<i32, LevelSave>);
+
+impl StoredLevels {
+    pub fn snapshot(&self) -> Vec<(i32, LevelSave)> {
+        self.0
+            .iter()
+            .map(|(level, save)| (*level, save.clone()))
+            .collect()
+    }
+}
+
+/// Asks for the player to be moved to another level
+pub struct ChangeLevel {
+    pub level: i32,
+    pub pos: Vec2,
+}
+
+/// Sent once the player is on a new level, the world gets regenerated for it
+pub struct LevelChanged;
+
+// Walking onto stairs takes the player up or down, they have to step off and back
+// on to go back the way they came
+fn use_stairs(
+    player_q: Query<&Transform, With<Player>>,
+    level: Res<CurrentLevel>,
+    generator: Res<WorldGenerator>,
+    edits: Res<WorldEdits>,
+    mut last_tile: Local<Option<IVec2>>,
+    mut ev_change_level: EventWriter<ChangeLevel>,
+) {
+    let tile = world_to_tile(player_q.single().translation.truncate());
+    if *last_tile == Some(tile) {
+        return;
+    }
+    *last_tile = Some(tile);
+
+    let to = match edits.tile_at(&generator, tile) {
+        TileType::StairsDown => level.0 - 1,
+        TileType::StairsUp => level.0 + 1,
+        _ => return,
+    };
+    // stairs sit on the same tile on both levels so the player comes out on the other end
+    ev_change_level.send(ChangeLevel {
+        level: to,
+        pos: tile_to_world(tile),
+    });
+}
+
+type LevelChangePlayerQuery<'a> = (&'a mut Transform, &'a mut Velocity);
+#[allow(clippy::too_many_arguments)]
+fn change_level(
+    mut commands: Commands,
+    mut ev_change_level: EventReader<ChangeLevel>,
+    mut ev_level_changed: EventWriter<LevelChanged>,
+    mut current: ResMut<CurrentLevel>,
+    mut stored: ResMut<StoredLevels>,
+    mut edits: ResMut<WorldEdits>,
+    mut harvested: ResMut<HarvestedDecorations>,
+    mut player_q: Query<LevelChangePlayerQuery, With<Player>>,
+    items_q: Query<(&Transform, &Item), Without<Player>>,
+    chests_q: Query<(&Transform, &Inventory), (With<Chest>, Without<Player>)>,
+    furniture_q: Query<(&Transform, &Furniture), (Without<Chest>, Without<Player>)>,
+    mobs_q: Query<(&Transform, &Health), (With<Mob>, Without<Player>)>,
+    level_entities: Query<Entity, Or<(With<Item>, With<Furniture>, With<Mob>)>>,
+    sprites: Res<SpriteAssets>,
+    item_db: Res<ItemDatabase>,
+) {
+    let change = match ev_change_level.iter().last() {
+        Some(change) => change,
+        None => return,
+    };
+    if change.level == current.0 {
+        return;
+    }
+
+    let leaving = LevelSave::capture(
+        &edits,
+        &harvested,
+        items_q.iter(),
+        chests_q.iter(),
+        furniture_q.iter(),
+        mobs_q.iter(),
+    );
+    stored.0.insert(current.0, leaving);
+    for entity in level_entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let arriving = stored.0.remove(&change.level).unwrap_or_default();
+    *edits = arriving.world_edits();
+    *harvested = arriving.harvested_decorations();
+    arriving.spawn_entities(&mut commands, &sprites, &item_db);
+    current.0 = change.level;
+
+    let (mut transform, mut velocity) = player_q.single_mut();
+    transform.translation.x = change.pos.x;
+    transform.translation.y = change.pos.y;
+    velocity.linvel = Vec2::ZERO;
+
+    info!("Moved to level {}", current.0);
+    ev_level_changed.send(LevelChanged);
+}
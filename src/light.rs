@@ -0,0 +1,250 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+};
+
+use crate::{
+    clock::WorldClock,
+    furniture::Furniture,
+    inventory::ActiveItem,
+    item_database::ItemDatabase,
+    level::{CurrentLevel, SKY_LEVEL, SURFACE_LEVEL},
+    map_generation::{tile_to_world, Chunk, WorldEdits, WorldGenerator, CHUNK_SIZE, TILE_SIZE},
+    player::Player,
+    tile_type::TileType,
+    AppState,
+};
+
+/*
+    Darkness is a black sprite over the view with a small texture in it, every frame
+    each pixel's alpha is worked out on the cpu from the lights around it. The texture
+    is coarse and stretched with linear filtering so the edges of the light come out soft.
+*/
+
+const NIGHT_DARKNESS: f32 = 0.85;
+const CAVE_DARKNESS: f32 = 0.95;
+// Relative to the camera, a little bigger than the view
+const OVERLAY_SIZE: Vec2 = Vec2::new(340., 260.);
+const Z_OVERLAY: f32 = -2.; // under the hud
+const MASK_CELL: f32 = 4.; // overlay units per pixel of the mask
+
+// Lava is lit a few tiles at a time instead of a light for every tile of it
+const LAVA_CELL: i32 = 4;
+const LAVA_LIGHT: f32 = 3.;
+
+pub struct LightPlugin;
+
+impl Plugin for LightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(spawn_darkness)
+                .with_system(light_furniture)
+                .with_system(light_lava)
+                .with_system(held_light)
+                .with_system(
+                    draw_darkness
+                        .after(light_furniture)
+                        .after(light_lava)
+                        .after(held_light),
+                ),
+        );
+    }
+}
+
+/// Lights up everything within `radius` tiles, fading out towards the edge
+#[derive(Component, Clone, Copy)]
+pub struct LightSource {
+    pub radius: f32,
+}
+
+/// The overlay that darkens everything under the hud
+#[derive(Component)]
+struct Darkness;
+
+// Hangs off of the game camera like the hud so it always covers the view
+fn spawn_darkness(
+    mut commands: Commands,
+    cameras: Query<Entity, Added<Camera2d>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for camera in cameras.iter() {
+        let size = (OVERLAY_SIZE / MASK_CELL).as_uvec2();
+        let mut mask = Image::new_fill(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0, 0, 0, 0],
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        // everything else is pixel art, this one is meant to be blurry
+        mask.sampler_descriptor = ImageSampler::linear();
+
+        let texture = images.add(mask);
+        commands.entity(camera).with_children(|parent| {
+            parent.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(OVERLAY_SIZE),
+                        ..default()
+                    },
+                    texture,
+                    transform: Transform::from_xyz(0., 0., Z_OVERLAY),
+                    ..default()
+                },
+                Darkness,
+            ));
+        });
+    }
+}
+
+fn light_furniture(
+    mut commands: Commands,
+    furniture: Query<(Entity, &Furniture), Added<Furniture>>,
+    item_db: Res<ItemDatabase>,
+) {
+    for (entity, piece) in furniture.iter() {
+        if let Some(radius) = item_db.light(piece.item) {
+            commands.entity(entity).insert(LightSource { radius });
+        }
+    }
+}
+
+// Chunks get a light for every few tiles of lava as children, the same way
+// decorations are put on them
+fn light_lava(
+    mut commands: Commands,
+    chunks: Query<(Entity, &Chunk), Added<Chunk>>,
+    generator: Res<WorldGenerator>,
+    edits: Res<WorldEdits>,
+) {
+    let size = CHUNK_SIZE as i32;
+    for (chunk_entity, chunk) in chunks.iter() {
+        let origin = chunk.pos * size;
+        let mut children = vec![];
+        for cell_x in (0..size).step_by(LAVA_CELL as usize) {
+            for cell_y in (0..size).step_by(LAVA_CELL as usize) {
+                let lava: Vec<IVec2> = (0..LAVA_CELL * LAVA_CELL)
+                    .map(|i| IVec2::new(cell_x + i % LAVA_CELL, cell_y + i / LAVA_CELL))
+                    .filter(|local| edits.tile_at(&generator, origin + *local) == TileType::Lava)
+                    .collect();
+                if lava.is_empty() {
+                    continue;
+                }
+                // in the middle of the lava, brighter the more of it there is
+                let center = lava.iter().map(|local| tile_to_world(*local)).sum::<Vec2>()
+                    / lava.len() as f32;
+                let radius =
+                    LAVA_LIGHT * (lava.len() as f32 / (LAVA_CELL * LAVA_CELL) as f32).sqrt();
+                children.push(
+                    commands
+                        .spawn((
+                            TransformBundle::from_transform(Transform::from_xyz(
+                                center.x, center.y, 0.,
+                            )),
+                            LightSource {
+                                radius: radius.max(1.5),
+                            },
+                        ))
+                        .id(),
+                );
+            }
+        }
+        commands.entity(chunk_entity).push_children(&children);
+    }
+}
+
+// Whatever the player is holding lights up around them if it gives off light
+fn held_light(
+    mut commands: Commands,
+    player_q: Query<(Entity, &ActiveItem, Option<&LightSource>), With<Player>>,
+    item_db: Res<ItemDatabase>,
+) {
+    for (player, active, light) in player_q.iter() {
+        let held = active.item.as_ref().and_then(|item| item_db.light(item.id));
+        match (held, light) {
+            (Some(radius), Some(light)) if light.radius == radius => {}
+            (Some(radius), _) => {
+                commands.entity(player).insert(LightSource { radius });
+            }
+            (None, Some(_)) => {
+                commands.entity(player).remove::<LightSource>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+// How dark it is away from any light, only the surface has nights and the sky is
+// always lit
+fn ambient_darkness(level: i32, clock: &WorldClock) -> f32 {
+    match level {
+        SURFACE_LEVEL => clock.night() * NIGHT_DARKNESS,
+        SKY_LEVEL => 0.,
+        _ => CAVE_DARKNESS,
+    }
+}
+
+fn draw_darkness(
+    clock: Res<WorldClock>,
+    level: Res<CurrentLevel>,
+    mut overlays: Query<(&Handle<Image>, &GlobalTransform, &mut Visibility), With<Darkness>>,
+    lights: Query<(&GlobalTransform, &LightSource)>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let darkness = ambient_darkness(level.0, &clock);
+    for (handle, overlay, mut visibility) in overlays.iter_mut() {
+        visibility.is_visible = darkness > 0.;
+        if darkness <= 0. {
+            continue;
+        }
+        let mask = match images.get_mut(handle) {
+            Some(mask) => mask,
+            None => continue,
+        };
+
+        let width = mask.texture_descriptor.size.width as usize;
+        let height = mask.texture_descriptor.size.height as usize;
+        // how lit each pixel is, from 0 to 1
+        let mut lit = vec![0f32; width * height];
+
+        // the overlay follows the camera's zoom so lights are worked out in its space
+        let to_overlay = overlay.compute_matrix().inverse();
+        let scale = overlay.compute_transform().scale.x;
+        let corner = -OVERLAY_SIZE / 2.;
+        for (transform, light) in lights.iter() {
+            let center = to_overlay
+                .transform_point3(transform.translation())
+                .truncate();
+            let radius = light.radius * TILE_SIZE / scale;
+            // only the pixels the light can reach
+            let min = ((center - radius - corner) / MASK_CELL)
+                .floor()
+                .max(Vec2::ZERO);
+            let max = ((center + radius - corner) / MASK_CELL)
+                .ceil()
+                .min(Vec2::new(width as f32, height as f32));
+            for y in min.y as usize..max.y as usize {
+                for x in min.x as usize..max.x as usize {
+                    let pixel = corner + (Vec2::new(x as f32, y as f32) + 0.5) * MASK_CELL;
+                    let falloff = 1. - (pixel.distance(center) / radius).powi(2);
+                    let cell = &mut lit[y * width + x];
+                    *cell = cell.max(falloff);
+                }
+            }
+        }
+
+        // textures go top to bottom
+        for (i, pixel) in mask.data.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (i % width, height - 1 - i / width);
+            let alpha = darkness * (1. - lit[y * width + x]);
+            pixel[3] = (alpha * 255.) as u8;
+        }
+    }
+}
@@ -1,24 +1,64 @@
 mod assetload;
+mod autotile;
+mod biome;
 mod camera;
+mod cave;
+mod chest;
+mod clock;
+mod controls;
+mod crafting;
+mod death;
 mod debug;
+mod decoration;
 mod engine;
+mod furniture;
+mod health;
+mod hotbar;
+mod hud;
 mod inventory;
 mod item;
+mod item_database;
+mod level;
+mod light;
 mod map_generation;
+mod menu;
+mod mining;
+mod mob;
 mod player;
+mod save;
 mod sound_event;
 mod states;
+mod tile_collider;
+mod tile_type;
 pub use assetload::FontAssets;
 pub use assetload::SpriteAssets;
+pub use autotile::AutotilePlugin;
 pub use camera::CameraPlugin;
+pub use chest::ChestPlugin;
+pub use clock::ClockPlugin;
+pub use controls::ControlsPlugin;
+pub use crafting::CraftingPlugin;
+pub use death::DeathPlugin;
+pub use decoration::DecorationPlugin;
 pub use engine::EnginePlugins;
+pub use furniture::FurniturePlugin;
+pub use health::HealthPlugin;
+pub use hotbar::HotbarPlugin;
+pub use hud::HudPlugin;
 pub use inventory::InventoryPlugin;
 pub use item::ItemPlugin;
+pub use level::LevelPlugin;
+pub use light::LightPlugin;
 pub use map_generation::MapGenerationPlugin;
+pub use menu::MenuPlugin;
+pub use mining::MiningPlugin;
+pub use mob::MobPlugin;
 pub use player::PlayerPlugin;
 pub use player::{Interact, PlayerEntity};
+pub use save::SavePlugin;
 pub use sound_event::GameSoundPlugin;
-pub use states::AppState;
+pub use states::{AppState, SessionEntity};
+pub use tile_collider::TileColliderPlugin;
 
 use bevy::prelude::*;
 use bevy_ecs_tilemap::TilemapPlugin;
@@ -33,15 +73,33 @@ fn main() {
             ..Default::default()
         })
         .add_plugin(RapierDebugRenderPlugin::default())
-        .add_system_set(SystemSet::on_update(AppState::InGame).with_system(display_events))
         .add_system_set(SystemSet::on_update(AppState::GameLoad).with_system(enter_game))
+        .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(despawn_session))
         .add_plugin(TilemapPlugin)
+        .add_plugin(ControlsPlugin)
         .add_plugin(PlayerPlugin)
         .add_plugin(CameraPlugin)
         .add_plugin(ItemPlugin)
         .add_plugin(InventoryPlugin)
+        .add_plugin(CraftingPlugin)
+        .add_plugin(MiningPlugin)
+        .add_plugin(ChestPlugin)
+        .add_plugin(FurniturePlugin)
+        .add_plugin(HealthPlugin)
+        .add_plugin(MobPlugin)
+        .add_plugin(HudPlugin)
+        .add_plugin(HotbarPlugin)
+        .add_plugin(DeathPlugin)
         .add_plugin(GameSoundPlugin)
         .add_plugin(MapGenerationPlugin)
+        .add_plugin(AutotilePlugin)
+        .add_plugin(DecorationPlugin)
+        .add_plugin(LevelPlugin)
+        .add_plugin(ClockPlugin)
+        .add_plugin(LightPlugin)
+        .add_plugin(TileColliderPlugin)
+        .add_plugin(SavePlugin)
+        .add_plugin(MenuPlugin)
         .run();
 }
 
@@ -53,16 +111,8 @@ fn enter_game(mut state: ResMut<State<AppState>>) {
     }
 }
 
-fn display_events(
-    mut collision_events: EventReader<CollisionEvent>,
-    mut contact_force_events: EventReader<ContactForceEvent>,
-    _player: Res<PlayerEntity>,
-) {
-    for collision_event in collision_events.iter() {
-        println!("Received collision event: {:?}", collision_event);
-    }
-
-    for contact_force_event in contact_force_events.iter() {
-        println!("Received contact force event: {:?}", contact_force_event);
+fn despawn_session(mut commands: Commands, session: Query<Entity, With<SessionEntity>>) {
+    for entity in session.iter() {
+        commands.entity(entity).despawn_recursive();
     }
 }
@@ -1,103 +1,624 @@
-use bevy::prelude::*;
+use std::sync::Mutex;
+
+use bevy::{prelude::*, utils::HashMap};
 use bevy_ecs_tilemap::prelude::*;
-use bevy_rapier2d::prelude::*;
 use bracket_noise::prelude::*;
 use rand::Rng;
 
-use crate::{AppState, SpriteAssets};
+use crate::{
+    autotile::{neighbour_mask, TileSheet},
+    biome::Biome,
+    cave,
+    decoration::Decoration,
+    level::{CurrentLevel, LevelChanged, LevelSystems, DEEPEST_LEVEL, SKY_LEVEL, SURFACE_LEVEL},
+    save::PendingLoad,
+    tile_collider::ColliderDirty,
+    tile_type::TileType,
+    AppState,
+};
 
 const Z_FLOOR: f32 = 0.;
 pub const TILE_SIZE: f32 = 16.; // coords are mulitplied by
+pub const CHUNK_SIZE: u32 = 32; // tiles along one side of a chunk
+const CHUNK_LOAD_RADIUS: i32 = 2; // chunks around the camera that are kept spawned
+const CHUNK_UNLOAD_RADIUS: i32 = 3; // a little bigger so walking along an edge doesn't thrash
+const MAX_CHUNKS_PER_FRAME: usize = 2;
+const DECORATION_CELL: i32 = 4; // tiles along one side of the area holding one decoration
+const STAIR_CELL: i32 = 48; // tiles along one side of the area holding one set of stairs
+const STAIR_CHANCE: f32 = 0.5;
 
 pub struct MapGenerationPlugin;
 
+/// A tilemap holding one CHUNK_SIZE x CHUNK_SIZE piece of the world
 #[derive(Component)]
-pub struct UnwalkableTile;
+pub struct Chunk {
+    pub pos: IVec2,
+}
 
-impl Plugin for MapGenerationPlugin {
-    fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(AppState::GameLoad).with_system(tm_startup));
+/// Sent whenever a tile in the world is changed after its chunk was generated
+pub struct TileChanged {
+    pub tile: IVec2,
+}
+
+/// Every chunk currently spawned, keyed by chunk coordinate
+#[derive(Resource, Default)]
+pub struct LoadedChunks(pub HashMap<IVec2, Entity>);
+
+impl LoadedChunks {
+    /// The tile entity at a world tile coordinate, None if its chunk isn't spawned
+    pub fn tile_entity(&self, storages: &Query<&TileStorage>, tile: IVec2) -> Option<Entity> {
+        let chunk = self.0.get(&tile_to_chunk(tile))?;
+        storages.get(*chunk).ok()?.get(&tile_to_local(tile))
+    }
+
+    /// The type of the tile at a world tile coordinate, None if its chunk isn't spawned
+    pub fn tile_type(
+        &self,
+        storages: &Query<&TileStorage>,
+        types: &Query<&TileType>,
+        tile: IVec2,
+    ) -> Option<TileType> {
+        let chunk = self.0.get(&tile_to_chunk(tile))?;
+        TileType::at(storages.get(*chunk).ok()?, types, &tile_to_local(tile))
     }
 }
 
-fn tm_startup(mut commands: Commands, tiles: Res<SpriteAssets>) {
-    let tilemap_size = TilemapSize { x: 500, y: 500 };
+/// Tiles that no longer match what the generator makes for them, these are laid over
+/// freshly generated chunks and are what gets written to a save
+#[derive(Resource, Default)]
+pub struct WorldEdits(pub HashMap<IVec2, TileType>);
 
-    let tilemap_entity = commands.spawn_empty().id();
-    let mut tile_storage = TileStorage::empty(tilemap_size);
+impl WorldEdits {
+    pub fn tile_at(&self, generator: &WorldGenerator, tile: IVec2) -> TileType {
+        match self.0.get(&tile) {
+            Some(tile_type) => *tile_type,
+            None => generator.tile_at(tile),
+        }
+    }
+}
+
+/// The seed every random decision in world generation is derived from,
+/// the same seed always produces the same map
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorldSeed(pub u64);
+
+impl WorldSeed {
+    /// Takes the seed from `--seed <seed>` on the command line, then the `MINICRAFT_SEED`
+    /// env var
+    pub fn from_env() -> Option<Self> {
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let value = match arg.strip_prefix("--seed=") {
+                Some(value) => Some(value.to_string()),
+                None if arg == "--seed" => args.next(),
+                None => None,
+            };
+            if let Some(value) = value {
+                return Some(WorldSeed::parse(&value));
+            }
+        }
+
+        std::env::var("MINICRAFT_SEED")
+            .ok()
+            .map(|value| WorldSeed::parse(&value))
+    }
+
+    pub fn random() -> Self {
+        WorldSeed(rand::thread_rng().gen())
+    }
+
+    /// Numbers are used as is, any other text is hashed so worlds can be shared by name
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        match text.parse::<u64>() {
+            Ok(seed) => WorldSeed(seed),
+            // FNV-1a, std's hasher is not guaranteed to be stable between releases
+            Err(_) => WorldSeed(text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            })),
+        }
+    }
+}
 
-    let mut rng = rand::thread_rng();
+impl Plugin for MapGenerationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WorldSeed::from_env().unwrap_or_else(WorldSeed::random))
+            .init_resource::<LoadedChunks>()
+            .init_resource::<WorldEdits>()
+            .add_event::<TileChanged>()
+            .add_system_set(SystemSet::on_enter(AppState::GameLoad).with_system(tm_startup))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    // same frame as the player moves, so the old level's colliders are
+                    // gone before physics runs again
+                    .with_system(
+                        reload_level
+                            .after(LevelSystems::Change)
+                            .before(spawn_chunks_around_camera),
+                    )
+                    .with_system(spawn_chunks_around_camera)
+                    .with_system(despawn_far_chunks),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(despawn_all_chunks));
+    }
+}
 
-    let seed = rng.gen::<u64>();
+/// Decides what tile sits at any position on a level, chunks are filled from this
+/// so a chunk always comes back the same after being despawned
+#[derive(Resource)]
+pub struct WorldGenerator {
+    seed: u64,
+    level: i32,
+    elevation: FastNoise,
+    moisture: FastNoise,
+    temperature: FastNoise,
+    // small scale noise that breaks biomes up into patches
+    detail: FastNoise,
+    // caves are carved a chunk at a time, so each chunk is only worked out once
+    caves: Mutex<HashMap<IVec2, Vec<bool>>>,
+}
 
-    // Perlin noise map, settings are tweakable
-    let mut noise = FastNoise::seeded(seed);
+// Every layer gets its own seed derived from the world seed so they don't line up
+fn noise_layer(seed: WorldSeed, layer: u64, octaves: i32, gain: f32, frequency: f32) -> FastNoise {
+    let mut noise = FastNoise::seeded(seed.0.wrapping_add(layer));
     noise.set_noise_type(NoiseType::SimplexFractal);
     noise.set_fractal_type(FractalType::FBM);
-    noise.set_fractal_octaves(6);
-    noise.set_fractal_gain(0.2);
+    noise.set_fractal_octaves(octaves);
+    noise.set_fractal_gain(gain);
     noise.set_fractal_lacunarity(2.0);
-    noise.set_frequency(1.5);
+    noise.set_frequency(frequency);
+    noise
+}
 
-    // Spawn the elements of the tilemap.
-    for x in 0..tilemap_size.x as u32 {
-        for y in 0..tilemap_size.y as u32 {
-            let tile_entity = commands.spawn_empty().id();
+impl WorldGenerator {
+    pub fn new(seed: WorldSeed, level: i32) -> Self {
+        // settings are tweakable, climate changes much slower than the land does
+        Self {
+            seed: seed.0,
+            level,
+            elevation: noise_layer(seed, 0, 6, 0.2, 1.5),
+            moisture: noise_layer(seed, 1, 3, 0.5, 0.6),
+            temperature: noise_layer(seed, 2, 3, 0.5, 0.4),
+            detail: noise_layer(seed, 3, 2, 0.5, 12.0),
+            caves: Mutex::new(HashMap::default()),
+        }
+    }
 
-            let tile_pos = TilePos { x, y };
+    // Noise value mapped into 0..1
+    fn sample(noise: &FastNoise, tile: IVec2) -> f32 {
+        let value = noise.get_noise((tile.x as f32) / 160.0, (tile.y as f32) / 100.0);
+        ((value + 1.0) * 0.5).clamp(0.0, 1.0)
+    }
 
-            let mut perlin_value = noise.get_noise((x as f32) / 160.0, (y as f32) / 100.0);
-            perlin_value = (perlin_value + 1.0) * 0.5;
-
-            let tile_index: u32;
-            if perlin_value > 0.75 {
-                tile_index = 4; // Water
-                commands.entity(tile_entity).insert(UnwalkableTile);
-            } else if perlin_value > 0.7 {
-                tile_index = 2; // Sand
-            } else if perlin_value > 0.2 {
-                tile_index = 0; // Grass
-            } else {
-                tile_index = 1; // Stone
-                commands.entity(tile_entity).insert(UnwalkableTile);
-            }
-            // println!("PVal: {} TileIndex: {}", perlin_value, tile_index);
+    pub fn biome_at(&self, tile: IVec2) -> Biome {
+        Biome::pick(
+            Self::sample(&self.elevation, tile),
+            Self::sample(&self.moisture, tile),
+            Self::sample(&self.temperature, tile),
+        )
+    }
+
+    /// The tile generated at the given world tile coordinate
+    pub fn tile_at(&self, tile: IVec2) -> TileType {
+        let base = match self.level {
+            SURFACE_LEVEL => self.surface_tile(tile),
+            SKY_LEVEL => self.sky_tile(tile),
+            _ => self.cave_tile(tile),
+        };
+        match self.stairs_near(tile) {
+            Some((stairs, stairs_type)) if stairs == tile => stairs_type,
+            // a little clearing so there is always somewhere to step off the stairs
+            Some(_) if !base.is_walkable() => self.floor_tile(),
+            _ => base,
+        }
+    }
+
+    fn surface_tile(&self, tile: IVec2) -> TileType {
+        let palette = self.biome_at(tile).palette();
+        let detail = Self::sample(&self.detail, tile);
+        palette
+            .patches
+            .iter()
+            .find(|(_, threshold)| detail > *threshold)
+            .map_or(palette.ground, |(patch, _)| *patch)
+    }
+
+    fn sky_tile(&self, tile: IVec2) -> TileType {
+        match Self::sample(&self.moisture, tile) > 0.5 {
+            true => TileType::Cloud,
+            false => TileType::Sky,
+        }
+    }
+
+    /// Drops the carved caves of chunks further than `radius` chunks from `center`,
+    /// they are carved again the same way if they're needed later
+    pub fn forget_caves(&self, center: IVec2, radius: i32) {
+        self.caves
+            .lock()
+            .unwrap()
+            .retain(|chunk, _| grid_distance(*chunk, center) <= radius);
+    }
 
-            commands.entity(tile_entity).insert(TileBundle {
-                position: tile_pos,
-                tilemap_id: TilemapId(tilemap_entity),
-                texture_index: TileTextureIndex(tile_index),
-                ..Default::default()
+    fn cave_tile(&self, tile: IVec2) -> TileType {
+        let chunk = tile_to_chunk(tile);
+        let local = tile_to_local(tile);
+        let wall = {
+            let mut caves = self.caves.lock().unwrap();
+            let walls = caves.entry(chunk).or_insert_with(|| {
+                // every level gets its own caves
+                let salt = 100 + (self.level - DEEPEST_LEVEL) as u64;
+                let size = CHUNK_SIZE as i32;
+                cave::carve(chunk * size, size, |tile| self.hash(tile, salt))
             });
-            tile_storage.set(&tile_pos, tile_entity);
+            walls[(local.y * CHUNK_SIZE + local.x) as usize]
+        };
+
+        if wall {
+            TileType::Stone
+        } else if self.level == DEEPEST_LEVEL && Self::sample(&self.detail, tile) > 0.7 {
+            TileType::Lava
+        } else {
+            TileType::Dirt
+        }
+    }
+
+    // What the ground is made of where nothing else is in the way
+    fn floor_tile(&self) -> TileType {
+        match self.level {
+            SKY_LEVEL => TileType::Cloud,
+            _ => TileType::Dirt,
+        }
+    }
+
+    /// Where the stairs between `upper` and the level below it are in a cell, if it has
+    /// any. Both levels work this out the same way so the two ends always line up
+    fn stairs_in_cell(&self, cell: IVec2, upper: i32) -> Option<IVec2> {
+        let salt = 200 + (upper - DEEPEST_LEVEL) as u64 * 3;
+        if self.hash(cell, salt) >= STAIR_CHANCE {
+            return None;
+        }
+        // stairs going down from odd and even levels use different halves of the cell,
+        // that way a level's stairs up and down never land on top of each other
+        let half = STAIR_CELL / 2;
+        let left = if upper.rem_euclid(2) == 0 {
+            2
+        } else {
+            half + 2
+        };
+        let offset = IVec2::new(
+            left + (self.hash(cell, salt + 1) * (half - 4) as f32) as i32,
+            2 + (self.hash(cell, salt + 2) * (STAIR_CELL - 4) as f32) as i32,
+        );
+        let stairs = cell * STAIR_CELL + offset;
+
+        // out at sea the clearing around the stairs would be a little island of floor,
+        // the surface noise is the same on every level so both ends can check it
+        let touches_surface = upper == SURFACE_LEVEL || upper == SKY_LEVEL;
+        let in_water = || {
+            self.biome_at(stairs) == Biome::Ocean || self.surface_tile(stairs) == TileType::Water
+        };
+        if touches_surface && in_water() {
+            return None;
+        }
+        Some(stairs)
+    }
+
+    /// Stairs on this tile or right next to it, along with which way they go
+    fn stairs_near(&self, tile: IVec2) -> Option<(IVec2, TileType)> {
+        let cell = IVec2::new(tile.x.div_euclid(STAIR_CELL), tile.y.div_euclid(STAIR_CELL));
+        let down = (self.level > DEEPEST_LEVEL)
+            .then(|| self.stairs_in_cell(cell, self.level))
+            .flatten()
+            .map(|stairs| (stairs, TileType::StairsDown));
+        let up = (self.level < SKY_LEVEL)
+            .then(|| self.stairs_in_cell(cell, self.level + 1))
+            .flatten()
+            .map(|stairs| (stairs, TileType::StairsUp));
+        [down, up]
+            .into_iter()
+            .flatten()
+            .find(|(stairs, _)| grid_distance(*stairs, tile) <= 1)
+    }
+
+    // Which decorations can show up on a tile and how likely each one is
+    fn decoration_rules(&self, tile: IVec2) -> &'static [(Decoration, f32)] {
+        match self.level {
+            SURFACE_LEVEL => self.biome_at(tile).palette().decorations,
+            SKY_LEVEL => &[],
+            // ore gets richer the deeper down it is
+            DEEPEST_LEVEL => &[(Decoration::IronOre, 0.3)],
+            _ => &[(Decoration::IronOre, 0.15)],
         }
     }
 
-    for y in 0..10 {
-        let rect_width = 5.;
-        let rect_height = 4.;
-        commands.spawn((
-            TransformBundle::from(Transform::from_xyz(
-                3. * TILE_SIZE,
-                y as f32 * TILE_SIZE,
-                Z_FLOOR,
-            )),
-            Collider::cuboid(TILE_SIZE / 2. * rect_width, TILE_SIZE / 2. * rect_height),
-        ));
+    /// A number in 0..1 that is always the same for a tile, `salt` gives independent
+    /// values for the same tile
+    pub fn hash(&self, tile: IVec2, salt: u64) -> f32 {
+        // splitmix64 finalizer
+        let mut x = self.seed
+            ^ (tile.x as u32 as u64).wrapping_mul(0x9E3779B97F4A7C15)
+            ^ (tile.y as u32 as u64).wrapping_mul(0xC2B2AE3D27D4EB4F)
+            ^ salt.wrapping_mul(0x165667B19E3779F9);
+        x = (x ^ (x >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94D049BB133111EB);
+        x ^= x >> 31;
+        (x >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// What grows or sits on a tile. The world is split into cells with at most one
+    /// decoration each at a random spot inside it, which keeps them spread apart
+    pub fn decoration_at(&self, tile: IVec2) -> Option<Decoration> {
+        let cell = IVec2::new(
+            tile.x.div_euclid(DECORATION_CELL),
+            tile.y.div_euclid(DECORATION_CELL),
+        );
+        let spot = cell * DECORATION_CELL
+            + IVec2::new(
+                (self.hash(cell, 1) * DECORATION_CELL as f32) as i32,
+                (self.hash(cell, 2) * DECORATION_CELL as f32) as i32,
+            );
+        if spot != tile || !self.tile_at(tile).is_walkable() || self.stairs_near(tile).is_some() {
+            return None;
+        }
+
+        let roll = self.hash(cell, 3);
+        let mut total = 0.;
+        self.decoration_rules(tile)
+            .iter()
+            .find(|(_, chance)| {
+                total += chance;
+                roll < total
+            })
+            .map(|(decoration, _)| *decoration)
+    }
+
+    /// Closest tile to the given one that can be stood on, searched in growing rings.
+    /// Trees and ore count as blocked
+    pub fn find_walkable_near(&self, tile: IVec2) -> IVec2 {
+        for radius in 0..CHUNK_SIZE as i32 * CHUNK_LOAD_RADIUS {
+            for x in -radius..=radius {
+                for y in -radius..=radius {
+                    let candidate = tile + IVec2::new(x, y);
+                    if grid_distance(candidate, tile) == radius
+                        && self.tile_at(candidate).is_walkable()
+                        && !self
+                            .decoration_at(candidate)
+                            .map_or(false, |decoration| decoration.is_solid())
+                    {
+                        return candidate;
+                    }
+                }
+            }
+        }
+        tile
+    }
+}
+
+/// Tile coordinate the world position falls on, tiles are centered on multiples of TILE_SIZE
+pub fn world_to_tile(pos: Vec2) -> IVec2 {
+    (pos / TILE_SIZE).round().as_ivec2()
+}
+
+pub fn tile_to_world(tile: IVec2) -> Vec2 {
+    tile.as_vec2() * TILE_SIZE
+}
+
+/// Chunk coordinate that contains the tile
+pub fn tile_to_chunk(tile: IVec2) -> IVec2 {
+    let size = CHUNK_SIZE as i32;
+    IVec2::new(tile.x.div_euclid(size), tile.y.div_euclid(size))
+}
+
+/// Position of the tile inside of its chunk's tilemap
+pub fn tile_to_local(tile: IVec2) -> TilePos {
+    let size = CHUNK_SIZE as i32;
+    TilePos {
+        x: tile.x.rem_euclid(size) as u32,
+        y: tile.y.rem_euclid(size) as u32,
+    }
+}
+
+fn grid_distance(a: IVec2, b: IVec2) -> i32 {
+    (a - b).abs().max_element()
+}
+
+fn tm_startup(
+    mut commands: Commands,
+    sheet: Res<TileSheet>,
+    seed: Res<WorldSeed>,
+    level: Res<CurrentLevel>,
+    edits: Res<WorldEdits>,
+    pending: Option<Res<PendingLoad>>,
+    mut loaded: ResMut<LoadedChunks>,
+) {
+    info!("Generating world with seed {}", seed.0);
+    let generator = WorldGenerator::new(*seed, level.0);
+
+    // Spawn everything around the player up front so they don't start in the void
+    let center = match pending {
+        Some(save) => tile_to_chunk(world_to_tile(Vec2::from(save.player.pos))),
+        None => IVec2::ZERO,
+    };
+    for x in -CHUNK_LOAD_RADIUS..=CHUNK_LOAD_RADIUS {
+        for y in -CHUNK_LOAD_RADIUS..=CHUNK_LOAD_RADIUS {
+            let chunk_pos = center + IVec2::new(x, y);
+            let chunk = spawn_chunk(&mut commands, &generator, &edits, &sheet, chunk_pos);
+            loaded.0.insert(chunk_pos, chunk);
+        }
+    }
+
+    commands.insert_resource(generator);
+}
+
+fn spawn_chunk(
+    commands: &mut Commands,
+    generator: &WorldGenerator,
+    edits: &WorldEdits,
+    sheet: &TileSheet,
+    chunk_pos: IVec2,
+) -> Entity {
+    let tilemap_size = TilemapSize {
+        x: CHUNK_SIZE,
+        y: CHUNK_SIZE,
+    };
+
+    let tilemap_entity = commands.spawn_empty().id();
+    let mut tile_storage = TileStorage::empty(tilemap_size);
+    let origin = chunk_pos * CHUNK_SIZE as i32;
+
+    // The chunk plus a tile of border all around, for working out the edges
+    let size = CHUNK_SIZE as i32;
+    let bordered = size + 2;
+    let grid: Vec<TileType> = (0..bordered * bordered)
+        .map(|i| {
+            edits.tile_at(
+                generator,
+                origin + IVec2::new(i % bordered - 1, i / bordered - 1),
+            )
+        })
+        .collect();
+    let grid_at = |local: IVec2| grid[((local.y + 1) * bordered + local.x + 1) as usize];
+
+    // Spawn the elements of the tilemap.
+    for x in 0..tilemap_size.x {
+        for y in 0..tilemap_size.y {
+            let tile_pos = TilePos { x, y };
+            let local = IVec2::new(x as i32, y as i32);
+            let tile_type = grid_at(local);
+            let mask = neighbour_mask(tile_type, |offset| grid_at(local + offset));
+            let (texture_index, animation) = sheet.tile_look(tile_type, mask);
+
+            let tile_entity = commands
+                .spawn((
+                    TileBundle {
+                        position: tile_pos,
+                        tilemap_id: TilemapId(tilemap_entity),
+                        texture_index,
+                        ..Default::default()
+                    },
+                    tile_type,
+                ))
+                .id();
+            if let Some(animation) = animation {
+                commands.entity(tile_entity).insert(animation);
+            }
+            tile_storage.set(&tile_pos, tile_entity);
+        }
     }
 
     let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
-    let grid_size = tile_size.into();
-    let map_type = TilemapType::default();
-
-    commands.entity(tilemap_entity).insert(TilemapBundle {
-        grid_size,
-        map_type,
-        size: tilemap_size,
-        storage: tile_storage,
-        texture: TilemapTexture::Single(tiles.tiles1.clone()),
-        tile_size,
-        transform: get_tilemap_center_transform(&tilemap_size, &grid_size, &map_type, Z_FLOOR),
-        ..Default::default()
-    });
+    let world_origin = tile_to_world(origin);
+
+    commands.entity(tilemap_entity).insert((
+        TilemapBundle {
+            grid_size: tile_size.into(),
+            map_type: TilemapType::default(),
+            size: tilemap_size,
+            storage: tile_storage,
+            texture: TilemapTexture::Single(sheet.texture.clone()),
+            tile_size,
+            transform: Transform::from_xyz(world_origin.x, world_origin.y, Z_FLOOR),
+            ..Default::default()
+        },
+        Chunk { pos: chunk_pos },
+        ColliderDirty,
+    ));
+
+    tilemap_entity
+}
+
+fn spawn_chunks_around_camera(
+    mut commands: Commands,
+    camera: Query<&Transform, With<Camera2d>>,
+    generator: Res<WorldGenerator>,
+    edits: Res<WorldEdits>,
+    sheet: Res<TileSheet>,
+    mut loaded: ResMut<LoadedChunks>,
+) {
+    let cam = match camera.get_single() {
+        Ok(cam) => cam,
+        Err(_) => return,
+    };
+    let center = tile_to_chunk(world_to_tile(cam.translation.truncate()));
+
+    let mut missing = Vec::new();
+    for x in -CHUNK_LOAD_RADIUS..=CHUNK_LOAD_RADIUS {
+        for y in -CHUNK_LOAD_RADIUS..=CHUNK_LOAD_RADIUS {
+            let chunk_pos = center + IVec2::new(x, y);
+            if !loaded.0.contains_key(&chunk_pos) {
+                missing.push(chunk_pos);
+            }
+        }
+    }
+
+    // Only a few chunks a frame so crossing a border doesn't hitch, closest ones first
+    missing.sort_by_key(|chunk_pos| grid_distance(*chunk_pos, center));
+    for chunk_pos in missing.into_iter().take(MAX_CHUNKS_PER_FRAME) {
+        let chunk = spawn_chunk(&mut commands, &generator, &edits, &sheet, chunk_pos);
+        loaded.0.insert(chunk_pos, chunk);
+    }
+}
+
+fn despawn_far_chunks(
+    mut commands: Commands,
+    camera: Query<&Transform, With<Camera2d>>,
+    chunks: Query<(Entity, &Chunk, &TileStorage)>,
+    mut loaded: ResMut<LoadedChunks>,
+    generator: Res<WorldGenerator>,
+) {
+    let cam = match camera.get_single() {
+        Ok(cam) => cam,
+        Err(_) => return,
+    };
+    let center = tile_to_chunk(world_to_tile(cam.translation.truncate()));
+
+    for (entity, chunk, storage) in chunks.iter() {
+        if grid_distance(chunk.pos, center) > CHUNK_UNLOAD_RADIUS {
+            despawn_chunk(&mut commands, entity, storage);
+            loaded.0.remove(&chunk.pos);
+        }
+    }
+    // anything looked up further out, like by spawn searches, goes too
+    generator.forget_caves(center, CHUNK_UNLOAD_RADIUS);
+}
+
+/// Tiles are not children of their tilemap so they have to be cleaned up by hand
+fn despawn_chunk(commands: &mut Commands, chunk: Entity, storage: &TileStorage) {
+    for tile in storage.iter().flatten() {
+        commands.entity(*tile).despawn();
+    }
+    commands.entity(chunk).despawn_recursive();
+}
+
+// Another level means another generator, the old level's chunks are thrown away
+// and the new level streams in around the camera
+fn reload_level(
+    mut commands: Commands,
+    mut ev_level_changed: EventReader<LevelChanged>,
+    seed: Res<WorldSeed>,
+    level: Res<CurrentLevel>,
+    mut generator: ResMut<WorldGenerator>,
+    chunks: Query<(Entity, &TileStorage), With<Chunk>>,
+    mut loaded: ResMut<LoadedChunks>,
+) {
+    if ev_level_changed.iter().last().is_none() {
+        return;
+    }
+    for (entity, storage) in chunks.iter() {
+        despawn_chunk(&mut commands, entity, storage);
+    }
+    loaded.0.clear();
+    *generator = WorldGenerator::new(*seed, level.0);
+}
+
+fn despawn_all_chunks(
+    mut commands: Commands,
+    chunks: Query<(Entity, &TileStorage), With<Chunk>>,
+    mut loaded: ResMut<LoadedChunks>,
+) {
+    for (entity, storage) in chunks.iter() {
+        despawn_chunk(&mut commands, entity, storage);
+    }
+    loaded.0.clear();
+    commands.remove_resource::<WorldGenerator>();
 }
@@ -0,0 +1,362 @@
+use bevy::{app::AppExit, prelude::*, ui::widget::ImageMode, window::ReceivedCharacter};
+
+use crate::{
+    clock::WorldClock,
+    controls::{Action, Actions},
+    decoration::HarvestedDecorations,
+    level::{CurrentLevel, StoredLevels},
+    map_generation::{WorldEdits, WorldSeed},
+    save::{ExitAfterSave, PendingLoad, SaveSlot, SAVE_SLOTS},
+    AppState, FontAssets, SpriteAssets,
+};
+
+const MENU_LINES: usize = 6;
+const MAX_SEED_LEN: usize = 20;
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuCursor>()
+            .init_resource::<NewGameOptions>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::MainMenu)
+                    .with_system(spawn_menu)
+                    .with_system(open_main_page),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::MainMenu)
+                    .with_system(main_menu_input)
+                    .with_system(draw_menu.after(main_menu_input)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::MainMenu).with_system(despawn_menu))
+            .add_system_set(
+                SystemSet::on_enter(AppState::NewGame)
+                    .with_system(spawn_menu)
+                    .with_system(open_new_game_page),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::NewGame)
+                    .with_system(new_game_input)
+                    .with_system(draw_menu.after(new_game_input)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::NewGame).with_system(despawn_menu))
+            .add_system_set(SystemSet::on_update(AppState::InGame).with_system(return_to_menu));
+    }
+}
+
+#[derive(PartialEq, Eq, Default)]
+enum MenuPage {
+    #[default]
+    Main,
+    LoadSlots,
+    NewGame,
+}
+
+#[derive(Resource, Default)]
+struct MenuCursor {
+    page: MenuPage,
+    selected: usize,
+}
+
+/// What the next new world will be made with, an empty seed rolls a random one
+#[derive(Resource)]
+pub struct NewGameOptions {
+    pub seed: String,
+    pub slot: u32,
+}
+
+impl Default for NewGameOptions {
+    fn default() -> Self {
+        Self {
+            // a seed passed on the command line is filled in for the player
+            seed: match WorldSeed::from_env() {
+                Some(seed) => seed.0.to_string(),
+                None => String::new(),
+            },
+            slot: 0,
+        }
+    }
+}
+
+// Everything spawned for the menu hangs off of this
+#[derive(Component)]
+struct MenuRoot;
+
+#[derive(Component)]
+struct MenuLine(usize);
+
+fn spawn_menu(mut commands: Commands, font: Res<FontAssets>, elements: Res<SpriteAssets>) {
+    let title_style = TextStyle {
+        font: font.celtic.clone(),
+        font_size: 40.0,
+        color: Color::BLACK,
+    };
+    let line_style = TextStyle {
+        font: font.monogram.clone(),
+        font_size: 28.0,
+        color: Color::BLACK,
+    };
+
+    // the ui has nothing to draw to until the game spawns its own camera
+    commands.spawn((Camera2dBundle::default(), MenuRoot));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            MenuRoot,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(ImageBundle {
+                    image: UiImage(elements.menu.clone()),
+                    image_mode: ImageMode::KeepAspect,
+                    style: Style {
+                        size: Size::new(Val::Px(280.), Val::Px(392.)),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(24.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|menu_parent| {
+                    menu_parent.spawn(
+                        TextBundle::from_section("MiniCraft", title_style.clone()).with_style(
+                            Style {
+                                margin: UiRect::all(Val::Px(16.)),
+                                ..default()
+                            },
+                        ),
+                    );
+                    for i in 0..MENU_LINES {
+                        menu_parent.spawn((
+                            TextBundle::from_section(String::new(), line_style.clone()).with_style(
+                                Style {
+                                    margin: UiRect::all(Val::Px(4.)),
+                                    ..default()
+                                },
+                            ),
+                            MenuLine(i),
+                        ));
+                    }
+                });
+        });
+}
+
+fn despawn_menu(mut commands: Commands, menu: Query<Entity, With<MenuRoot>>) {
+    for entity in menu.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn open_main_page(mut cursor: ResMut<MenuCursor>) {
+    cursor.page = MenuPage::Main;
+    cursor.selected = 0;
+}
+
+fn open_new_game_page(mut cursor: ResMut<MenuCursor>) {
+    cursor.page = MenuPage::NewGame;
+    cursor.selected = 0;
+}
+
+/// The text of every entry on the page the cursor is on
+fn page_entries(page: &MenuPage, options: &NewGameOptions) -> Vec<String> {
+    match page {
+        MenuPage::Main => vec![
+            "New Game".to_string(),
+            "Load Game".to_string(),
+            "Quit".to_string(),
+        ],
+        MenuPage::LoadSlots => {
+            let mut entries: Vec<String> = (0..SAVE_SLOTS)
+                .map(|slot| {
+                    if SaveSlot(slot).exists() {
+                        format!("Slot {}", slot + 1)
+                    } else {
+                        format!("Slot {} - empty", slot + 1)
+                    }
+                })
+                .collect();
+            entries.push("Back".to_string());
+            entries
+        }
+        MenuPage::NewGame => vec![
+            if options.seed.is_empty() {
+                "Seed: random".to_string()
+            } else {
+                format!("Seed: {}", options.seed)
+            },
+            format!("Save Slot: < {} >", options.slot + 1),
+            "Start".to_string(),
+            "Back".to_string(),
+        ],
+    }
+}
+
+fn move_cursor(actions: &Actions, cursor: &mut MenuCursor, entries: usize) {
+    if actions.just_pressed(Action::MenuUp) {
+        cursor.selected = (cursor.selected + entries - 1) % entries;
+    }
+    if actions.just_pressed(Action::MenuDown) {
+        cursor.selected = (cursor.selected + 1) % entries;
+    }
+}
+
+fn main_menu_input(
+    actions: Res<Actions>,
+    mut cursor: ResMut<MenuCursor>,
+    options: Res<NewGameOptions>,
+    mut state: ResMut<State<AppState>>,
+    mut slot: ResMut<SaveSlot>,
+    mut ev_exit: EventWriter<AppExit>,
+) {
+    let entries = page_entries(&cursor.page, &options).len();
+    move_cursor(&actions, &mut cursor, entries);
+
+    if actions.just_pressed(Action::Back) && cursor.page == MenuPage::LoadSlots {
+        cursor.page = MenuPage::Main;
+        cursor.selected = 1;
+        return;
+    }
+    if !actions.just_pressed(Action::Confirm) {
+        return;
+    }
+
+    match cursor.page {
+        MenuPage::Main => match cursor.selected {
+            0 => state
+                .set(AppState::NewGame)
+                .expect("Failed to change states"),
+            1 => {
+                cursor.page = MenuPage::LoadSlots;
+                cursor.selected = 0;
+            }
+            _ => ev_exit.send(AppExit),
+        },
+        MenuPage::LoadSlots => {
+            let picked = cursor.selected as u32;
+            if picked == SAVE_SLOTS {
+                cursor.page = MenuPage::Main;
+                cursor.selected = 1;
+            } else if SaveSlot(picked).exists() {
+                slot.0 = picked;
+                state
+                    .set(AppState::LoadGame)
+                    .expect("Failed to change states");
+            }
+        }
+        MenuPage::NewGame => {}
+    }
+}
+
+fn new_game_input(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    // the seed is text, so backspace stays a raw key rather than an action
+    keeb_input: Res<Input<KeyCode>>,
+    mut ev_chars: EventReader<ReceivedCharacter>,
+    mut cursor: ResMut<MenuCursor>,
+    mut options: ResMut<NewGameOptions>,
+    mut state: ResMut<State<AppState>>,
+) {
+    let entries = page_entries(&cursor.page, &options).len();
+    let typed: Vec<char> = ev_chars.iter().map(|ev| ev.char).collect();
+    // letters typed into the seed can also be bound to menu movement, so a keypress that
+    // typed something doesn't move off of the seed, and one that moved onto it isn't typed
+    let line = cursor.selected;
+    if line != 0 || typed.is_empty() {
+        move_cursor(&actions, &mut cursor, entries);
+    }
+
+    match line {
+        // seed, typed in directly
+        0 => {
+            for c in typed {
+                if c.is_ascii_alphanumeric() && options.seed.len() < MAX_SEED_LEN {
+                    options.seed.push(c);
+                }
+            }
+            if keeb_input.just_pressed(KeyCode::Back) {
+                options.seed.pop();
+            }
+        }
+        // save slot
+        1 => {
+            if actions.just_pressed(Action::MenuLeft) {
+                options.slot = (options.slot + SAVE_SLOTS - 1) % SAVE_SLOTS;
+            }
+            if actions.just_pressed(Action::MenuRight) {
+                options.slot = (options.slot + 1) % SAVE_SLOTS;
+            }
+        }
+        _ => {}
+    }
+
+    if actions.just_pressed(Action::Back)
+        || (cursor.selected == 3 && actions.just_pressed(Action::Confirm))
+    {
+        state
+            .set(AppState::MainMenu)
+            .expect("Failed to change states");
+    } else if cursor.selected == 2 && actions.just_pressed(Action::Confirm) {
+        let seed = if options.seed.is_empty() {
+            WorldSeed::random()
+        } else {
+            WorldSeed::parse(&options.seed)
+        };
+        commands.insert_resource(seed);
+        commands.insert_resource(WorldEdits::default());
+        commands.insert_resource(HarvestedDecorations::default());
+        commands.insert_resource(CurrentLevel::default());
+        commands.insert_resource(StoredLevels::default());
+        commands.insert_resource(WorldClock::default());
+        commands.insert_resource(SaveSlot(options.slot));
+        commands.remove_resource::<PendingLoad>();
+        state
+            .set(AppState::GameLoad)
+            .expect("Failed to change states");
+    }
+}
+
+fn draw_menu(
+    cursor: Res<MenuCursor>,
+    options: Res<NewGameOptions>,
+    mut lines: Query<(&mut Text, &MenuLine)>,
+) {
+    let entries = page_entries(&cursor.page, &options);
+    for (mut text, line) in lines.iter_mut() {
+        let section = &mut text.sections[0];
+        match entries.get(line.0) {
+            Some(entry) if line.0 == cursor.selected => {
+                section.value = format!("> {} <", entry);
+                section.style.color = Color::MAROON;
+            }
+            Some(entry) => {
+                section.value = entry.clone();
+                section.style.color = Color::BLACK;
+            }
+            None => section.value = String::new(),
+        }
+    }
+}
+
+// The game is saved on the way out so leaving never loses anything
+fn return_to_menu(
+    actions: Res<Actions>,
+    mut state: ResMut<State<AppState>>,
+    mut exit: ResMut<ExitAfterSave>,
+) {
+    if actions.just_pressed(Action::ExitToMenu) {
+        exit.0 = true;
+        state.push(AppState::Save).expect("Failed to change states");
+    }
+}
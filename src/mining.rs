@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use rand::Rng;
+
+use crate::{
+    controls::{Action, Actions},
+    health::Stamina,
+    inventory::{ActiveItem, MenuWindow},
+    item::spawn_ground_item,
+    item_database::ItemDatabase,
+    map_generation::{tile_to_world, world_to_tile, LoadedChunks, TileChanged, WorldEdits},
+    player::{Direction, PSystems, Player},
+    tile_type::{TileSound, TileType},
+    AppState, SpriteAssets,
+};
+
+pub struct MiningPlugin;
+
+impl Plugin for MiningPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TileHit>().add_system_set(
+            SystemSet::on_update(AppState::InGame).with_system(mine_tile.after(PSystems::Harvest)),
+        );
+    }
+}
+
+/// Damage a tile has taken so far, it is lost if the tile's chunk is despawned
+#[derive(Component)]
+pub struct TileDamage(pub u32);
+
+//Events
+/// A tile took a swing, whether or not it broke
+pub struct TileHit {
+    pub sound: TileSound,
+}
+
+type MinedTileQuery<'a> = (
+    &'a mut TileType,
+    &'a mut TileColor,
+    Option<&'a mut TileDamage>,
+);
+#[allow(clippy::too_many_arguments)]
+fn mine_tile(
+    mut commands: Commands,
+    actions: Res<Actions>,
+    mut player_q: Query<(&Transform, &Direction, &ActiveItem, &mut Stamina), With<Player>>,
+    loaded: Res<LoadedChunks>,
+    storages: Query<&TileStorage>,
+    mut tiles: Query<MinedTileQuery>,
+    item_db: Res<ItemDatabase>,
+    sprites: Res<SpriteAssets>,
+    mut edits: ResMut<WorldEdits>,
+    mut ev_tile_changed: EventWriter<TileChanged>,
+    mut ev_tile_hit: EventWriter<TileHit>,
+    menus: Query<&Visibility, With<MenuWindow>>,
+) {
+    if !actions.just_pressed(Action::Attack) || menus.iter().any(|menu| menu.is_visible) {
+        return;
+    }
+
+    let (transform, direction, active, mut stamina) = player_q.single_mut();
+    let target = world_to_tile(transform.translation.truncate()) + direction.offset();
+    let tile = match loaded.tile_entity(&storages, target) {
+        Some(tile) => tile,
+        None => return,
+    };
+    let (mut tile_type, mut color, damage) = match tiles.get_mut(tile) {
+        Ok(tile) => tile,
+        Err(_) => return,
+    };
+    let props = tile_type.props();
+    let hardness = match props.hardness {
+        Some(hardness) => hardness,
+        None => return,
+    };
+    // too tired to swing
+    if !stamina.try_use(1) {
+        return;
+    }
+
+    let hit = match props.tool.and_then(|kind| active.tool(&item_db, kind)) {
+        Some(tool) => 1 + tool.power,
+        None => 1,
+    };
+    let total = damage.as_ref().map_or(0, |damage| damage.0) + hit;
+
+    ev_tile_hit.send(TileHit { sound: props.sound });
+
+    if total < hardness {
+        // darken the tile a little more with every hit
+        let shade = 1. - 0.5 * total as f32 / hardness as f32;
+        color.0 = Color::rgb(shade, shade, shade);
+        match damage {
+            Some(mut damage) => damage.0 = total,
+            None => {
+                commands.entity(tile).insert(TileDamage(total));
+            }
+        }
+        return;
+    }
+
+    // the texture and collider follow once the chunk sees the change
+    *tile_type = props.becomes;
+    color.0 = Color::WHITE;
+    commands.entity(tile).remove::<TileDamage>();
+    edits.0.insert(target, props.becomes);
+    ev_tile_changed.send(TileChanged { tile: target });
+
+    let mut rng = rand::thread_rng();
+    let center = tile_to_world(target);
+    for (name, max) in props.drops {
+        for _ in 0..rng.gen_range(1..=*max) {
+            let jitter = Vec2::new(rng.gen_range(-4.0..4.0), rng.gen_range(-4.0..4.0));
+            spawn_ground_item(
+                &mut commands,
+                &sprites,
+                &item_db,
+                item_db.item(name, 1),
+                center + jitter,
+            );
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+
+use crate::{
+    clock::{Dawn, Dusk, WorldClock},
+    controls::{Action, Actions},
+    health::{DamageEvent, Health, Invulnerable, Stamina},
+    inventory::{ActiveItem, MenuWindow},
+    item_database::{ItemDatabase, ToolKind},
+    level::{CurrentLevel, LevelChanged, SURFACE_LEVEL},
+    map_generation::{tile_to_chunk, world_to_tile, LoadedChunks, WorldEdits, WorldGenerator},
+    player::{Direction, PSystems, Player},
+    AppState, SessionEntity, SpriteAssets,
+};
+
+const Z_MOB: f32 = 39.;
+const MAX_MOBS: usize = 8;
+const SPAWN_INTERVAL: f32 = 4.;
+const MOB_HEALTH: i32 = 5;
+const NIGHT_SPAWN_INTERVAL: f32 = 1.5;
+const SPAWN_MIN_DIST: f32 = 180.; // just off the edge of the screen
+const SPAWN_MAX_DIST: f32 = 320.;
+const DESPAWN_DIST: f32 = 640.;
+const MOB_SPEED: f32 = 40.;
+const CHASE_RADIUS: f32 = 96.;
+const CONTACT_RADIUS: f32 = 10.;
+const ATTACK_REACH: f32 = 10.;
+const KNOCKBACK: f32 = 200.;
+
+pub struct MobPlugin;
+
+impl Plugin for MobPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MobSpawnTimer(Timer::from_seconds(
+            SPAWN_INTERVAL,
+            TimerMode::Repeating,
+        )))
+        .add_event::<MobHurt>()
+        .add_event::<MobDied>()
+        .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(set_spawn_rate))
+        .add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(night_spawn_rate)
+                .with_system(spawn_mobs.after(night_spawn_rate))
+                .with_system(despawn_far_mobs)
+                .with_system(mob_ai)
+                .with_system(tick_stun)
+                .with_system(contact_damage)
+                .with_system(player_attack.label(PSystems::Attack).after(PSystems::Input)),
+        )
+        .add_system_set(SystemSet::on_pause(AppState::InGame).with_system(halt_mobs));
+    }
+}
+
+#[derive(Component)]
+pub struct Mob {
+    pub damage: i32,
+}
+
+#[derive(Component)]
+enum MobAi {
+    Wander { dir: Vec2, timer: Timer },
+    Chase,
+}
+
+/// Knocked back and not in control of where it's going
+#[derive(Component, Deref, DerefMut)]
+struct Stunned(Timer);
+
+#[derive(Resource, Deref, DerefMut)]
+struct MobSpawnTimer(Timer);
+
+//Events
+pub struct MobHurt;
+
+pub struct MobDied;
+
+/// Puts a mob in the world, `health` is how much of its health it has left
+pub fn spawn_mob(
+    commands: &mut Commands,
+    sprites: &SpriteAssets,
+    pos: Vec2,
+    health: i32,
+) -> Entity {
+    commands
+        .spawn((
+            SpriteSheetBundle {
+                texture_atlas: sprites.player_move.clone(),
+                sprite: TextureAtlasSprite {
+                    color: Color::rgb(0.4, 0.9, 0.4), // zombies
+                    ..default()
+                },
+                transform: Transform::from_xyz(pos.x, pos.y, Z_MOB),
+                ..default()
+            },
+            Mob { damage: 1 },
+            Health {
+                current: health.min(MOB_HEALTH),
+                max: MOB_HEALTH,
+            },
+            MobAi::Chase,
+            RigidBody::Dynamic,
+            Velocity::zero(),
+            Collider::ball(5.),
+            LockedAxes::ROTATION_LOCKED,
+            // can't budge furniture
+            Dominance::group(-1),
+            SessionEntity,
+        ))
+        .id()
+}
+
+// Only the surface has nights, under ground and up in the sky it's the same all day
+fn spawn_interval(level: i32, clock: &WorldClock) -> f32 {
+    if level == SURFACE_LEVEL && clock.is_night() {
+        NIGHT_SPAWN_INTERVAL
+    } else {
+        SPAWN_INTERVAL
+    }
+}
+
+// Picks the spawn rate for whatever time it is when a game starts or is loaded
+fn set_spawn_rate(
+    clock: Res<WorldClock>,
+    level: Res<CurrentLevel>,
+    mut timer: ResMut<MobSpawnTimer>,
+) {
+    timer.set_duration(Duration::from_secs_f32(spawn_interval(level.0, &clock)));
+}
+
+// Mobs come out much more often at night, the rate is picked again when the sun
+// comes up or goes down and when the player moves to another level
+fn night_spawn_rate(
+    mut ev_dawn: EventReader<Dawn>,
+    mut ev_dusk: EventReader<Dusk>,
+    mut ev_level_changed: EventReader<LevelChanged>,
+    clock: Res<WorldClock>,
+    level: Res<CurrentLevel>,
+    mut timer: ResMut<MobSpawnTimer>,
+) {
+    let dawn = ev_dawn.iter().count() > 0;
+    let dusk = ev_dusk.iter().count() > 0;
+    let moved = ev_level_changed.iter().count() > 0;
+    if dawn || dusk || moved {
+        timer.set_duration(Duration::from_secs_f32(spawn_interval(level.0, &clock)));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_mobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut timer: ResMut<MobSpawnTimer>,
+    player_q: Query<&Transform, With<Player>>,
+    mobs: Query<(), With<Mob>>,
+    generator: Res<WorldGenerator>,
+    edits: Res<WorldEdits>,
+    loaded: Res<LoadedChunks>,
+    sprites: Res<SpriteAssets>,
+) {
+    if !timer.tick(time.delta()).just_finished() || mobs.iter().count() >= MAX_MOBS {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+    let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+    let dist = rng.gen_range(SPAWN_MIN_DIST..SPAWN_MAX_DIST);
+    let pos = player_q.single().translation.truncate() + Vec2::from_angle(angle) * dist;
+
+    // only on open ground that is spawned in, otherwise try again next time
+    let tile = world_to_tile(pos);
+    let spawned = loaded.0.contains_key(&tile_to_chunk(tile));
+    if spawned && edits.tile_at(&generator, tile).is_walkable() {
+        spawn_mob(&mut commands, &sprites, pos, MOB_HEALTH);
+    }
+}
+
+fn despawn_far_mobs(
+    mut commands: Commands,
+    player_q: Query<&Transform, With<Player>>,
+    mobs: Query<(Entity, &Transform), (With<Mob>, Without<Player>)>,
+) {
+    let player = player_q.single().translation.truncate();
+    for (mob, transform) in mobs.iter() {
+        if transform.translation.truncate().distance(player) > DESPAWN_DIST {
+            commands.entity(mob).despawn_recursive();
+        }
+    }
+}
+
+// Wanders around until the player comes close then walks straight at them
+type MobAiQuery<'a> = (
+    &'a Transform,
+    &'a mut Velocity,
+    &'a mut MobAi,
+    &'a mut TextureAtlasSprite,
+);
+fn mob_ai(
+    time: Res<Time>,
+    player_q: Query<&Transform, With<Player>>,
+    mut mobs: Query<MobAiQuery, (With<Mob>, Without<Player>, Without<Stunned>)>,
+) {
+    let player = player_q.single().translation.truncate();
+    let mut rng = rand::thread_rng();
+
+    for (transform, mut velocity, mut ai, mut sprite) in mobs.iter_mut() {
+        let to_player = player - transform.translation.truncate();
+        let chasing = to_player.length() < CHASE_RADIUS;
+
+        match (&mut *ai, chasing) {
+            (MobAi::Chase, true) => velocity.linvel = to_player.normalize_or_zero() * MOB_SPEED,
+            (MobAi::Wander { dir, timer }, false) => {
+                if timer.tick(time.delta()).just_finished() {
+                    // stand still every so often
+                    *dir = match rng.gen_bool(0.3) {
+                        true => Vec2::ZERO,
+                        false => Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU)),
+                    };
+                    *timer = Timer::from_seconds(rng.gen_range(1.0..3.0), TimerMode::Once);
+                }
+                velocity.linvel = *dir * MOB_SPEED * 0.5;
+            }
+            (_, true) => *ai = MobAi::Chase,
+            (_, false) => {
+                *ai = MobAi::Wander {
+                    dir: Vec2::ZERO,
+                    timer: Timer::from_seconds(1.0, TimerMode::Once),
+                }
+            }
+        }
+
+        // face where it's headed, same sheet layout as the player
+        let linvel = velocity.linvel;
+        if linvel != Vec2::ZERO {
+            if linvel.x.abs() > linvel.y.abs() {
+                sprite.index = 2;
+                sprite.flip_x = linvel.x < 0.;
+            } else {
+                sprite.index = if linvel.y > 0. { 1 } else { 0 };
+            }
+        }
+    }
+}
+
+fn tick_stun(mut commands: Commands, time: Res<Time>, mut stunned: Query<(Entity, &mut Stunned)>) {
+    for (entity, mut stun) in stunned.iter_mut() {
+        if stun.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Stunned>();
+        }
+    }
+}
+
+// Stops mobs sliding around on their last velocity while the game is paused
+fn halt_mobs(mut mobs: Query<&mut Velocity, With<Mob>>) {
+    for mut velocity in mobs.iter_mut() {
+        velocity.linvel = Vec2::ZERO;
+    }
+}
+
+fn contact_damage(
+    player_q: Query<(Entity, &Transform), (With<Player>, Without<Invulnerable>)>,
+    mobs: Query<(&Transform, &Mob), Without<Player>>,
+    mut ev_damage: EventWriter<DamageEvent>,
+) {
+    let (player, transform) = match player_q.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let pos = transform.translation.truncate();
+
+    if let Some((_, mob)) = mobs
+        .iter()
+        .find(|(mob, _)| mob.translation.truncate().distance(pos) < CONTACT_RADIUS)
+    {
+        ev_damage.send(DamageEvent {
+            target: player,
+            amount: mob.damage,
+        });
+    }
+}
+
+// Attacking hits whatever mob is right in front of the player, if nothing is there
+// the press is left for mining. Every swing that connects costs stamina
+#[allow(clippy::too_many_arguments)]
+fn player_attack(
+    mut commands: Commands,
+    mut actions: ResMut<Actions>,
+    mut player_q: Query<(&Transform, &Direction, &ActiveItem, &mut Stamina), With<Player>>,
+    mut mobs: Query<(Entity, &Transform, &mut Health, &mut Velocity), (With<Mob>, Without<Player>)>,
+    item_db: Res<ItemDatabase>,
+    menus: Query<&Visibility, With<MenuWindow>>,
+    mut ev_mob_hurt: EventWriter<MobHurt>,
+    mut ev_mob_died: EventWriter<MobDied>,
+) {
+    if !actions.just_pressed(Action::Attack) || menus.iter().any(|menu| menu.is_visible) {
+        return;
+    }
+
+    let (transform, direction, active, mut stamina) = player_q.single_mut();
+    let facing = direction.offset().as_vec2();
+    let reach = transform.translation.truncate() + facing * ATTACK_REACH;
+    let in_reach = |mob: &Transform| mob.translation.truncate().distance(reach) <= ATTACK_REACH;
+    if !mobs.iter().any(|(_, mob, ..)| in_reach(mob)) {
+        return;
+    }
+
+    // the swing is used up on the mob even when there is no energy left for it
+    actions.clear_just_pressed(Action::Attack);
+    if !stamina.try_use(1) {
+        return;
+    }
+
+    let damage = match active.tool(&item_db, ToolKind::Sword) {
+        Some(sword) => 1 + sword.power as i32,
+        None => 1,
+    };
+
+    for (mob, mob_transform, mut health, mut velocity) in mobs.iter_mut() {
+        if !in_reach(mob_transform) {
+            continue;
+        }
+
+        if health.damage(damage) {
+            commands.entity(mob).despawn_recursive();
+            ev_mob_died.send(MobDied);
+        } else {
+            velocity.linvel = facing * KNOCKBACK;
+            commands
+                .entity(mob)
+                .insert(Stunned(Timer::from_seconds(0.25, TimerMode::Once)));
+            ev_mob_hurt.send(MobHurt);
+        }
+    }
+}
@@ -1,16 +1,28 @@
 use crate::{
-    inventory::{Inventory, InventoryUpdate, ItemPickup},
-    item::Item,
-    SpriteAssets,
+    controls::{Action, Actions},
+    health::{Health, Stamina},
+    inventory::{ActiveItem, Inventory, ItemPickup, MenuWindow},
+    item::{Item, PickupCooldown},
+    item_database::ItemDatabase,
+    level::SURFACE_LEVEL,
+    map_generation::{tile_to_world, world_to_tile, WorldEdits, WorldGenerator, WorldSeed},
+    SessionEntity, SpriteAssets,
 };
 
 use super::AppState;
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashSet};
 use bevy_inspector_egui::{Inspectable, RegisterInspectable};
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 const Z_PLAYER: f32 = 40.;
 const PLAYER_SPEED: f32 = 80.0;
+const SWIM_SPEED: f32 = 40.;
+const MAGNET_RADIUS: f32 = 24.;
+const MAGNET_SPEED: f32 = 60.;
+
+/// The player's collision group, water only keeps out everything that isn't in it
+pub const PLAYER_GROUP: Group = Group::GROUP_1;
 
 pub struct PlayerPlugin;
 
@@ -26,6 +38,7 @@ impl Plugin for PlayerPlugin {
                             .before(PSystems::Input),
                     )
                     .with_system(direction_animation)
+                    .with_system(attract_items.after(PSystems::Movement))
                     .with_system(
                         pickup_item
                             .label(Interact::Caller)
@@ -40,6 +53,8 @@ impl Plugin for PlayerPlugin {
 pub enum PSystems {
     Input,
     Movement,
+    Attack,
+    Harvest,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
@@ -49,13 +64,25 @@ pub enum Interact {
     _Ui,
 }
 
-#[derive(Component)]
-enum Direction {
+#[derive(Component, Clone, Copy, Serialize, Deserialize)]
+pub enum Direction {
     Up,
     Down,
     Left,
     Right,
 }
+
+impl Direction {
+    /// One tile in the direction being faced
+    pub fn offset(&self) -> IVec2 {
+        match self {
+            Direction::Up => IVec2::Y,
+            Direction::Down => IVec2::NEG_Y,
+            Direction::Left => IVec2::NEG_X,
+            Direction::Right => IVec2::X,
+        }
+    }
+}
 #[derive(Component, Inspectable)]
 pub struct Player;
 
@@ -76,12 +103,20 @@ pub struct InputCapture {
 #[derive(Resource)]
 pub struct PlayerEntity(pub Entity);
 
-fn startup(mut commands: Commands, sprites: Res<SpriteAssets>) {
+/// Where the player comes back after dying
+#[derive(Resource)]
+pub struct SpawnPoint(pub Vec2);
+
+fn startup(mut commands: Commands, sprites: Res<SpriteAssets>, seed: Res<WorldSeed>) {
+    // don't start the player stuck inside of stone or water, the spawn is always on
+    // the surface even when a save puts the player somewhere else
+    let surface = WorldGenerator::new(*seed, SURFACE_LEVEL);
+    let spawn = tile_to_world(surface.find_walkable_near(IVec2::ZERO));
     let player_entity = commands
         .spawn((
             SpriteSheetBundle {
                 texture_atlas: sprites.player_move.clone(),
-                transform: Transform::from_xyz(10., 0., Z_PLAYER),
+                transform: Transform::from_xyz(spawn.x, spawn.y, Z_PLAYER),
                 ..Default::default()
             },
             Player,
@@ -92,15 +127,25 @@ fn startup(mut commands: Commands, sprites: Res<SpriteAssets>) {
             },
             Direction::Down,
             Inventory::new(20),
-            RigidBody::Dynamic,
-            Velocity::zero(),
-            Collider::round_cuboid(1.0, 1.0, 0.05),
-            ActiveEvents::COLLISION_EVENTS,
-            LockedAxes::ROTATION_LOCKED,
+            ActiveItem::default(),
+            Health::new(10),
+            Stamina::new(10),
+            (
+                RigidBody::Dynamic,
+                Velocity::zero(),
+                Collider::round_cuboid(1.0, 1.0, 0.05),
+                ActiveEvents::COLLISION_EVENTS,
+                CollisionGroups::new(PLAYER_GROUP, Group::ALL),
+                LockedAxes::ROTATION_LOCKED,
+                // pushes furniture and mobs out of the way without being pushed back
+                Dominance::group(1),
+            ),
+            SessionEntity,
         ))
         .id();
 
     commands.insert_resource(PlayerEntity(player_entity));
+    commands.insert_resource(SpawnPoint(spawn));
 }
 
 fn direction_animation(
@@ -144,70 +189,147 @@ fn direction_animation(
 }
 
 fn player_input(
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<Actions>,
     mut q: Query<(&mut InputCapture, &mut Direction), With<Player>>,
+    menus: Query<&Visibility, With<MenuWindow>>,
 ) {
     let (mut input, mut dir) = q.single_mut();
     input.movement = Vec2::ZERO;
 
-    if keyboard_input.pressed(KeyCode::A) {
+    // the movement keys belong to the menus while they are open
+    if menus.iter().any(|menu| menu.is_visible) {
+        return;
+    }
+
+    if actions.pressed(Action::MoveLeft) {
         input.movement.x -= 1.0;
         *dir = Direction::Left;
     }
-    if keyboard_input.pressed(KeyCode::D) {
+    if actions.pressed(Action::MoveRight) {
         input.movement.x += 1.0;
         *dir = Direction::Right;
     }
-    if keyboard_input.pressed(KeyCode::W) {
+    if actions.pressed(Action::MoveUp) {
         input.movement.y += 1.0;
         *dir = Direction::Up;
     }
-    if keyboard_input.pressed(KeyCode::S) {
+    if actions.pressed(Action::MoveDown) {
         input.movement.y -= 1.0;
         *dir = Direction::Down;
     }
 }
 
-// Velocity based movement
+type MovePlayerQuery<'a> = (
+    &'a Transform,
+    &'a mut Velocity,
+    &'a mut PlayerState,
+    &'a InputCapture,
+);
+// Velocity based movement, slower while swimming
 fn move_player(
-    _time: Res<Time>,
-    mut q: Query<(&mut Velocity, &mut PlayerState, &InputCapture), With<Player>>,
+    mut q: Query<MovePlayerQuery, With<Player>>,
+    generator: Res<WorldGenerator>,
+    edits: Res<WorldEdits>,
 ) {
-    let (mut velocity, mut state, input_val) = q.single_mut();
+    let (transform, mut velocity, mut state, input_val) = q.single_mut();
+    let under = edits.tile_at(&generator, world_to_tile(transform.translation.truncate()));
+    let speed = match under.props().swimmable {
+        true => SWIM_SPEED,
+        false => PLAYER_SPEED,
+    };
 
     let move_delta = Vec2::new(input_val.movement.x, input_val.movement.y);
 
     if input_val.movement != Vec2::ZERO {
         *state = PlayerState::Moving;
-        velocity.linvel = move_delta * PLAYER_SPEED;
+        velocity.linvel = move_delta * speed;
     } else {
         *state = PlayerState::Idle;
         velocity.linvel = Vec2::ZERO;
     }
 }
 
-type InventoryQuery<'a> = (&'a Transform, Entity, &'a Inventory);
-type ItemQuery<'a> = (&'a Transform, Entity, &'a Item);
-//A collider system may be more advantageous
+// Ground items carry sensors, so the player's collider touching one is what picks it up.
+// An item that didn't all fit is kept until the player steps off of it and tried again
+// each frame, so it gets picked up as soon as there is room
 fn pickup_item(
-    player_q: Query<InventoryQuery, (With<Player>, Without<Item>)>,
-    items_q: Query<ItemQuery, (Without<Inventory>, Without<Player>)>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut touching: Local<HashSet<Entity>>,
+    player_e: Res<PlayerEntity>,
+    player_q: Query<&Inventory, With<Player>>,
+    items_q: Query<&Item>,
+    item_db: Res<ItemDatabase>,
     mut ev_itempickup: EventWriter<ItemPickup>,
-    mut ev_inventory_update: EventWriter<InventoryUpdate>,
 ) {
-    let (player, who, _inv) = player_q.single();
-    for (transform, item, info) in items_q.iter() {
-        let item_pos = Vec2::new(transform.translation.x, transform.translation.y);
-        let player_pos = Vec2::new(player.translation.x, player.translation.y);
+    for collision in collision_events.iter() {
+        let (a, b, started) = match collision {
+            CollisionEvent::Started(a, b, _) => (*a, *b, true),
+            CollisionEvent::Stopped(a, b, _) => (*a, *b, false),
+        };
+        let item = match (a == player_e.0, b == player_e.0) {
+            (true, _) => b,
+            (_, true) => a,
+            _ => continue,
+        };
+        if started {
+            touching.insert(item);
+        } else {
+            touching.remove(&item);
+        }
+    }
 
-        let dist = item_pos.distance(player_pos);
-        if dist < 8.0 {
+    let inventory = player_q.single();
+    touching.retain(|item| {
+        let info = match items_q.get(*item) {
+            Ok(info) => info,
+            // picked up and despawned
+            Err(_) => return false,
+        };
+        if inventory.room_for(info.id, &item_db) > 0 {
             ev_itempickup.send(ItemPickup {
-                item,
-                what_item: info.name.to_string(),
-                who,
+                item: *item,
+                what_item: info.id,
+                who: player_e.0,
             });
-            ev_inventory_update.send(InventoryUpdate);
+        }
+        true
+    });
+}
+
+type MagnetItemQuery<'a> = (&'a mut Transform, &'a Item);
+// Items close to the player drift towards them, only what the physics world
+// finds inside the radius is looked at. Items that wouldn't fit are left where they are
+fn attract_items(
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    player_q: Query<(&Transform, &Inventory), With<Player>>,
+    mut items_q: Query<MagnetItemQuery, (Without<Player>, Without<PickupCooldown>)>,
+    item_db: Res<ItemDatabase>,
+) {
+    let (player_transform, inventory) = player_q.single();
+    let player = player_transform.translation.truncate();
+    let mut nearby = vec![];
+    rapier_context.intersections_with_shape(
+        player,
+        0.,
+        &Collider::ball(MAGNET_RADIUS),
+        QueryFilter::default().exclude_solids(),
+        |entity| {
+            nearby.push(entity);
+            true
+        },
+    );
+
+    let step = MAGNET_SPEED * time.delta_seconds();
+    for entity in nearby {
+        if let Ok((mut transform, item)) = items_q.get_mut(entity) {
+            if inventory.room_for(item.id, &item_db) == 0 {
+                continue;
+            }
+            let pos = transform.translation.truncate();
+            let moved = pos + (player - pos).clamp_length_max(step);
+            transform.translation.x = moved.x;
+            transform.translation.y = moved.y;
         }
     }
 }
@@ -0,0 +1,403 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chest::{spawn_chest, Chest},
+    clock::WorldClock,
+    controls::{Action, Actions},
+    decoration::HarvestedDecorations,
+    furniture::{spawn_furniture, Furniture},
+    health::Health,
+    inventory::{Inventory, InventoryUpdate},
+    item::{spawn_ground_item, Item},
+    item_database::{ItemDatabase, ItemId},
+    level::{CurrentLevel, StoredLevels},
+    map_generation::{WorldEdits, WorldSeed},
+    mob::{spawn_mob, Mob},
+    player::{Direction, Player},
+    tile_type::TileType,
+    AppState, SpriteAssets,
+};
+
+const SAVE_DIR: &str = "saves";
+pub const SAVE_SLOTS: u32 = 3;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveSlot>()
+            .init_resource::<ExitAfterSave>()
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(save_load_input)
+                    .with_system(restore_save),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Save).with_system(write_save))
+            .add_system_set(SystemSet::on_update(AppState::Save).with_system(finish_save))
+            .add_system_set(SystemSet::on_enter(AppState::LoadGame).with_system(read_save));
+    }
+}
+
+/// Which save file is written to and read from
+#[derive(Resource, Default)]
+pub struct SaveSlot(pub u32);
+
+impl SaveSlot {
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from(SAVE_DIR).join(format!("slot_{}.ron", self.0))
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path().is_file()
+    }
+}
+
+/// Set when the game is saved on the way out to the main menu
+#[derive(Resource, Default)]
+pub struct ExitAfterSave(pub bool);
+
+/// Everything needed to put a play session back together, the world itself is
+/// regenerated from the seed so only tiles that were changed are stored.
+/// The top level fields are the level the player is on
+#[derive(Serialize, Deserialize)]
+pub struct SaveData {
+    pub seed: u64,
+    pub edits: Vec<([i32; 2], TileType)>,
+    pub player: PlayerSave,
+    pub ground_items: Vec<GroundItemSave>,
+    #[serde(default)]
+    pub chests: Vec<ChestSave>,
+    #[serde(default)]
+    pub furniture: Vec<FurnitureSave>,
+    #[serde(default)]
+    pub mobs: Vec<MobSave>,
+    /// Tiles whose tree or ore has been taken
+    #[serde(default)]
+    pub harvested: Vec<[i32; 2]>,
+    #[serde(default)]
+    pub level: i32,
+    /// Every other level that has been visited
+    #[serde(default)]
+    pub other_levels: Vec<(i32, LevelSave)>,
+    #[serde(default)]
+    pub clock: WorldClock,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlayerSave {
+    pub pos: [f32; 2],
+    pub direction: Direction,
+    pub inventory: Vec<Item>,
+    pub capacity: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GroundItemSave {
+    pub item: Item,
+    pub pos: [f32; 2],
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ChestSave {
+    pub pos: [f32; 2],
+    pub items: Vec<Item>,
+    pub capacity: i32,
+}
+
+/// Furniture that isn't a chest, those are saved with their contents above
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FurnitureSave {
+    pub item: ItemId,
+    pub pos: [f32; 2],
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MobSave {
+    pub pos: [f32; 2],
+    pub health: i32,
+}
+
+/// The state of one level of the world, kept while the player is on another one
+/// and written to the save
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct LevelSave {
+    #[serde(default)]
+    pub edits: Vec<([i32; 2], TileType)>,
+    #[serde(default)]
+    pub ground_items: Vec<GroundItemSave>,
+    #[serde(default)]
+    pub chests: Vec<ChestSave>,
+    #[serde(default)]
+    pub furniture: Vec<FurnitureSave>,
+    #[serde(default)]
+    pub mobs: Vec<MobSave>,
+    #[serde(default)]
+    pub harvested: Vec<[i32; 2]>,
+}
+
+impl LevelSave {
+    pub fn capture<'a>(
+        edits: &WorldEdits,
+        harvested: &HarvestedDecorations,
+        items: impl Iterator<Item = (&'a Transform, &'a Item)>,
+        chests: impl Iterator<Item = (&'a Transform, &'a Inventory)>,
+        furniture: impl Iterator<Item = (&'a Transform, &'a Furniture)>,
+        mobs: impl Iterator<Item = (&'a Transform, &'a Health)>,
+    ) -> Self {
+        LevelSave {
+            edits: edits
+                .0
+                .iter()
+                .map(|(tile, tile_type)| (tile.to_array(), *tile_type))
+                .collect(),
+            ground_items: items
+                .map(|(transform, item)| GroundItemSave {
+                    item: item.clone(),
+                    pos: transform.translation.truncate().to_array(),
+                })
+                .collect(),
+            chests: chests
+                .map(|(transform, inventory)| ChestSave {
+                    pos: transform.translation.truncate().to_array(),
+                    items: inventory.items.clone(),
+                    capacity: inventory.capacity,
+                })
+                .collect(),
+            furniture: furniture
+                .map(|(transform, furniture)| FurnitureSave {
+                    item: furniture.item,
+                    pos: transform.translation.truncate().to_array(),
+                })
+                .collect(),
+            mobs: mobs
+                .map(|(transform, health)| MobSave {
+                    pos: transform.translation.truncate().to_array(),
+                    health: health.current,
+                })
+                .collect(),
+            harvested: harvested.0.iter().map(|tile| tile.to_array()).collect(),
+        }
+    }
+
+    pub fn world_edits(&self) -> WorldEdits {
+        WorldEdits(
+            self.edits
+                .iter()
+                .map(|(tile, tile_type)| (IVec2::from(*tile), *tile_type))
+                .collect(),
+        )
+    }
+
+    pub fn harvested_decorations(&self) -> HarvestedDecorations {
+        HarvestedDecorations(
+            self.harvested
+                .iter()
+                .map(|tile| IVec2::from(*tile))
+                .collect(),
+        )
+    }
+
+    /// Puts the level's items, chests, furniture and mobs back into the world
+    pub fn spawn_entities(
+        &self,
+        commands: &mut Commands,
+        sprites: &SpriteAssets,
+        item_db: &ItemDatabase,
+    ) {
+        for ground_item in self.ground_items.iter() {
+            spawn_ground_item(
+                commands,
+                sprites,
+                item_db,
+                ground_item.item.clone(),
+                Vec2::from(ground_item.pos),
+            );
+        }
+
+        for chest in self.chests.iter() {
+            let mut inventory = Inventory::new(chest.capacity);
+            inventory.items = chest.items.clone();
+            spawn_chest(commands, sprites, item_db, Vec2::from(chest.pos), inventory);
+        }
+
+        for furniture in self.furniture.iter() {
+            spawn_furniture(
+                commands,
+                sprites,
+                item_db,
+                furniture.item,
+                Vec2::from(furniture.pos),
+            );
+        }
+
+        for mob in self.mobs.iter() {
+            spawn_mob(commands, sprites, Vec2::from(mob.pos), mob.health);
+        }
+    }
+}
+
+/// A save that was read from disk and is waiting for the world to be spawned to be applied
+#[derive(Resource)]
+pub struct PendingLoad {
+    pub player: PlayerSave,
+    pub level: LevelSave,
+}
+
+fn save_load_input(actions: Res<Actions>, slot: Res<SaveSlot>, mut state: ResMut<State<AppState>>) {
+    if actions.just_pressed(Action::Save) {
+        // pushed so the game is paused instead of torn down while saving
+        state.push(AppState::Save).expect("Failed to change states");
+    } else if actions.just_pressed(Action::Load) {
+        if slot.exists() {
+            state
+                .set(AppState::LoadGame)
+                .expect("Failed to change states");
+        } else {
+            warn!("No save in slot {} to load", slot.0);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_save(
+    slot: Res<SaveSlot>,
+    seed: Res<WorldSeed>,
+    edits: Res<WorldEdits>,
+    harvested: Res<HarvestedDecorations>,
+    current_level: Res<CurrentLevel>,
+    stored_levels: Res<StoredLevels>,
+    clock: Res<WorldClock>,
+    player_q: Query<(&Transform, &Direction, &Inventory), With<Player>>,
+    items_q: Query<(&Transform, &Item), Without<Player>>,
+    chests_q: Query<(&Transform, &Inventory), (With<Chest>, Without<Player>)>,
+    furniture_q: Query<(&Transform, &Furniture), Without<Chest>>,
+    mobs_q: Query<(&Transform, &Health), (With<Mob>, Without<Player>)>,
+    mut exit: ResMut<ExitAfterSave>,
+) {
+    let (transform, direction, inventory) = player_q.single();
+    let level = LevelSave::capture(
+        &edits,
+        &harvested,
+        items_q.iter(),
+        chests_q.iter(),
+        furniture_q.iter(),
+        mobs_q.iter(),
+    );
+
+    let data = SaveData {
+        seed: seed.0,
+        edits: level.edits,
+        player: PlayerSave {
+            pos: transform.translation.truncate().to_array(),
+            direction: *direction,
+            inventory: inventory.items.clone(),
+            capacity: inventory.capacity,
+        },
+        ground_items: level.ground_items,
+        chests: level.chests,
+        furniture: level.furniture,
+        mobs: level.mobs,
+        harvested: level.harvested,
+        level: current_level.0,
+        other_levels: stored_levels.snapshot(),
+        clock: *clock,
+    };
+
+    let result = ron::ser::to_string_pretty(&data, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())
+        .and_then(|text| {
+            fs::create_dir_all(SAVE_DIR).map_err(|err| err.to_string())?;
+            fs::write(slot.path(), text).map_err(|err| err.to_string())
+        });
+
+    match result {
+        Ok(()) => info!("Saved game to {}", slot.path().display()),
+        Err(err) => {
+            error!("Could not save the game: {}", err);
+            // staying in the game is better than throwing it away
+            exit.0 = false;
+        }
+    }
+}
+
+fn finish_save(mut state: ResMut<State<AppState>>, mut exit: ResMut<ExitAfterSave>) {
+    if exit.0 {
+        exit.0 = false;
+        state
+            .replace(AppState::MainMenu)
+            .expect("Failed to change states");
+    } else {
+        state.pop().expect("Failed to change states");
+    }
+}
+
+fn read_save(mut commands: Commands, slot: Res<SaveSlot>, mut state: ResMut<State<AppState>>) {
+    let result = fs::read_to_string(slot.path())
+        .map_err(|err| err.to_string())
+        .and_then(|text| ron::from_str::<SaveData>(&text).map_err(|err| err.to_string()));
+
+    match result {
+        Ok(data) => {
+            let level = LevelSave {
+                edits: data.edits,
+                ground_items: data.ground_items,
+                chests: data.chests,
+                furniture: data.furniture,
+                mobs: data.mobs,
+                harvested: data.harvested,
+            };
+            commands.insert_resource(WorldSeed(data.seed));
+            commands.insert_resource(level.world_edits());
+            commands.insert_resource(level.harvested_decorations());
+            commands.insert_resource(CurrentLevel(data.level));
+            commands.insert_resource(StoredLevels(data.other_levels.into_iter().collect()));
+            commands.insert_resource(data.clock);
+            commands.insert_resource(PendingLoad {
+                player: data.player,
+                level,
+            });
+        }
+        // the world that was left is generated again so the player isn't stranded
+        Err(err) => error!("Could not load {}: {}", slot.path().display(), err),
+    }
+
+    state
+        .set(AppState::GameLoad)
+        .expect("Failed to change states");
+}
+
+// Runs once the player has been spawned and hands it everything from the save
+fn restore_save(
+    mut commands: Commands,
+    pending: Option<Res<PendingLoad>>,
+    mut player_q: Query<(&mut Transform, &mut Direction, &mut Inventory), With<Player>>,
+    sprites: Res<SpriteAssets>,
+    item_db: Res<ItemDatabase>,
+    mut ev_inventory_update: EventWriter<InventoryUpdate>,
+) {
+    let pending = match pending {
+        Some(pending) => pending,
+        None => return,
+    };
+    let (mut transform, mut direction, mut inventory) = match player_q.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    let player = &pending.player;
+    transform.translation.x = player.pos[0];
+    transform.translation.y = player.pos[1];
+    *direction = player.direction;
+    inventory.items = player.inventory.clone();
+    inventory.capacity = player.capacity;
+
+    pending
+        .level
+        .spawn_entities(&mut commands, &sprites, &item_db);
+
+    ev_inventory_update.send(InventoryUpdate);
+    commands.remove_resource::<PendingLoad>();
+}
@@ -1,12 +1,30 @@
 use bevy::prelude::*;
 use bevy_kira_audio::{Audio, AudioControl};
 
-use crate::{assetload::SoundAssets, inventory::PlayerPickupSuccess, AppState};
+use crate::{
+    assetload::SoundAssets,
+    crafting::CraftSuccess,
+    health::PlayerHurt,
+    inventory::PlayerPickupSuccess,
+    mining::TileHit,
+    mob::{MobDied, MobHurt},
+    tile_type::TileSound,
+    AppState,
+};
 pub struct GameSoundPlugin;
 
 impl Plugin for GameSoundPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_update(AppState::InGame).with_system(p_item_pickup_sfx));
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(p_item_pickup_sfx)
+                .with_system(p_craft_sfx)
+                .with_system(mob_hurt_sfx)
+                .with_system(mob_death_sfx)
+                .with_system(p_hurt_sfx)
+                .with_system(tile_hit_sfx),
+        )
+        .add_system_set(SystemSet::on_enter(AppState::Dead).with_system(p_death_sfx));
     }
 }
 
@@ -19,3 +37,67 @@ pub fn p_item_pickup_sfx(
         audio.play(noises.item_pickup.clone()).with_volume(0.05);
     }
 }
+
+pub fn p_craft_sfx(
+    mut ev_crafted: EventReader<CraftSuccess>,
+    noises: Res<SoundAssets>,
+    audio: Res<Audio>,
+) {
+    for _ in ev_crafted.iter() {
+        audio.play(noises.craft.clone()).with_volume(0.05);
+    }
+}
+
+pub fn mob_hurt_sfx(
+    mut ev_mob_hurt: EventReader<MobHurt>,
+    noises: Res<SoundAssets>,
+    audio: Res<Audio>,
+) {
+    for _ in ev_mob_hurt.iter() {
+        audio.play(noises.monster_hurt.clone()).with_volume(0.05);
+    }
+}
+
+pub fn mob_death_sfx(
+    mut ev_mob_died: EventReader<MobDied>,
+    noises: Res<SoundAssets>,
+    audio: Res<Audio>,
+) {
+    for _ in ev_mob_died.iter() {
+        audio.play(noises.death.clone()).with_volume(0.05);
+    }
+}
+
+pub fn p_hurt_sfx(
+    mut ev_player_hurt: EventReader<PlayerHurt>,
+    noises: Res<SoundAssets>,
+    audio: Res<Audio>,
+) {
+    for _ in ev_player_hurt.iter() {
+        audio.play(noises.player_hurt.clone()).with_volume(0.05);
+    }
+}
+
+// Every kind of tile plays the same sound pitched up or down
+pub fn tile_hit_sfx(
+    mut ev_tile_hit: EventReader<TileHit>,
+    noises: Res<SoundAssets>,
+    audio: Res<Audio>,
+) {
+    for ev in ev_tile_hit.iter() {
+        let rate = match ev.sound {
+            TileSound::Soft => 1.4,
+            TileSound::Crunch => 1.2,
+            TileSound::Hard => 0.8,
+            TileSound::Splash => 0.6,
+        };
+        audio
+            .play(noises.tile_hit.clone())
+            .with_playback_rate(rate)
+            .with_volume(0.05);
+    }
+}
+
+pub fn p_death_sfx(noises: Res<SoundAssets>, audio: Res<Audio>) {
+    audio.play(noises.death.clone()).with_volume(0.05);
+}
@@ -10,6 +10,8 @@ Some talk about how I will implement states in the game and how many we need
 
     then either:
         Game Loop --> Save
+        or:
+        Game Loop --> Dead --> Game Loop
 
         do:
         --> Main Menu
@@ -24,8 +26,11 @@ Some talk about how I will implement states in the game and how many we need
         Game Load
         Game Loop
         Saving
+        Dead
 */
 
+use bevy::prelude::Component;
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum AppState {
     AssetLoad,
@@ -35,4 +40,9 @@ pub enum AppState {
     GameLoad,
     InGame,
     Save,
+    Dead,
 }
+
+/// Marks entities that belong to a play session, they are despawned when leaving InGame
+#[derive(Component)]
+pub struct SessionEntity;
@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    map_generation::{tile_to_chunk, Chunk, LoadedChunks, TileChanged, CHUNK_SIZE, TILE_SIZE},
+    player::PLAYER_GROUP,
+    tile_type::TileType,
+    AppState,
+};
+
+/*
+    Static colliders for the world, see "Bulk Collider Creation" in planning.md
+
+    Making a collider per tile takes far too long so every chunk merges its unwalkable
+    tiles into as few rectangles as it can and puts them on a single compound collider.
+    When a tile changes only the chunk it is in gets rebuilt.
+
+    Tiles that can be swum through get a compound collider of their own that only
+    the player passes through, it holds back mobs and anything else.
+*/
+
+pub struct TileColliderPlugin;
+
+impl Plugin for TileColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(mark_changed_chunks.before(rebuild_chunk_colliders))
+                .with_system(rebuild_chunk_colliders),
+        );
+    }
+}
+
+/// A compound collider child of a chunk, one for its solid tiles and one for its water
+#[derive(Component)]
+pub struct ChunkCollider;
+
+/// Chunk needs its collider made again, chunks are spawned with this
+#[derive(Component)]
+pub struct ColliderDirty;
+
+fn mark_changed_chunks(
+    mut commands: Commands,
+    mut ev_tile_changed: EventReader<TileChanged>,
+    loaded: Res<LoadedChunks>,
+) {
+    for ev in ev_tile_changed.iter() {
+        if let Some(chunk) = loaded.0.get(&tile_to_chunk(ev.tile)) {
+            commands.entity(*chunk).insert(ColliderDirty);
+        }
+    }
+}
+
+type DirtyChunkQuery<'a> = (Entity, &'a TileStorage, Option<&'a Children>);
+fn rebuild_chunk_colliders(
+    mut commands: Commands,
+    dirty_chunks: Query<DirtyChunkQuery, (With<Chunk>, With<ColliderDirty>)>,
+    types: Query<&TileType>,
+    old_colliders: Query<(), With<ChunkCollider>>,
+) {
+    let size = CHUNK_SIZE as usize;
+    for (chunk, storage, children) in dirty_chunks.iter() {
+        commands.entity(chunk).remove::<ColliderDirty>();
+
+        if let Some(children) = children {
+            for child in children.iter() {
+                if old_colliders.contains(*child) {
+                    commands.entity(*child).despawn();
+                }
+            }
+        }
+
+        let mut solid = vec![false; size * size];
+        let mut water = vec![false; size * size];
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let tile_type = TileType::at(storage, &types, &TilePos { x, y });
+                if let Some(tile_type) = tile_type.filter(|tile_type| !tile_type.is_walkable()) {
+                    let i = y as usize * size + x as usize;
+                    match tile_type.props().swimmable {
+                        true => water[i] = true,
+                        false => solid[i] = true,
+                    }
+                }
+            }
+        }
+
+        let groups = [
+            (solid, CollisionGroups::default()),
+            (water, CollisionGroups::new(Group::ALL, !PLAYER_GROUP)),
+        ];
+        for (cells, groups) in groups {
+            let shapes: Vec<(Vect, Rot, Collider)> = merge_rects(&cells, size)
+                .into_iter()
+                .map(|(min, rect_size)| {
+                    // tile 0,0 is centered on the chunk's origin
+                    let center = (min.as_vec2() + rect_size.as_vec2() / 2. - 0.5) * TILE_SIZE;
+                    let half = rect_size.as_vec2() * TILE_SIZE / 2.;
+                    (center, 0., Collider::cuboid(half.x, half.y))
+                })
+                .collect();
+
+            if shapes.is_empty() {
+                continue;
+            }
+
+            let collider = commands
+                .spawn((
+                    TransformBundle::default(),
+                    Collider::compound(shapes),
+                    groups,
+                    ChunkCollider,
+                ))
+                .id();
+            commands.entity(chunk).add_child(collider);
+        }
+    }
+}
+
+/// Greedily covers the solid cells of a size x size grid with rectangles, each one grows
+/// as wide as it can then as tall as it can. Returns the min corner and size in cells.
+fn merge_rects(solid: &[bool], size: usize) -> Vec<(UVec2, UVec2)> {
+    let mut used = vec![false; solid.len()];
+    let mut rects = vec![];
+    let free = |used: &[bool], i: usize| solid[i] && !used[i];
+
+    for y in 0..size {
+        for x in 0..size {
+            if !free(&used, y * size + x) {
+                continue;
+            }
+
+            let mut w = 1;
+            while x + w < size && free(&used, y * size + x + w) {
+                w += 1;
+            }
+
+            let mut h = 1;
+            while y + h < size && (x..x + w).all(|cx| free(&used, (y + h) * size + cx)) {
+                h += 1;
+            }
+
+            for cy in y..y + h {
+                for cx in x..x + w {
+                    used[cy * size + cx] = true;
+                }
+            }
+            rects.push((
+                UVec2::new(x as u32, y as u32),
+                UVec2::new(w as u32, h as u32),
+            ));
+        }
+    }
+
+    rects
+}
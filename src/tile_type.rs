@@ -0,0 +1,207 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::item_database::ToolKind;
+
+/*
+    Tile art
+
+    Neither tile sheet is checked in, so the indices in the registry are the layout
+    the game expects of them rather than something read off of the art. tiles.png
+    starts with the terrain the map was first drawn with, grass, stone, sand, dirt and
+    water, at the indices the original tilemap used for them. tiles2.png holds the
+    terrain added since in a row from its first tile: snow, ice, mud and dark grass
+    for biomes, then lava, stairs down, stairs up, cloud and sky for the other levels.
+    If the art is laid out differently it's these numbers that change.
+
+    Every index is checked against the size of its sheet once they're loaded, see
+    autotile.rs, so a sheet that's missing tiles shows up in the log.
+*/
+
+/// Every kind of tile the world is made of. Every tile entity has one, and it is
+/// what saves store, by the number it's given here, so those must never change
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u32", into = "u32")]
+pub enum TileType {
+    Grass = 0,
+    Stone = 1,
+    Sand = 2,
+    Dirt = 3,
+    Water = 4,
+    Snow = 5,
+    Ice = 6,
+    Mud = 7,
+    DarkGrass = 8,
+    Lava = 9,
+    StairsDown = 10,
+    StairsUp = 11,
+    Cloud = 12,
+    /// The open air between clouds
+    Sky = 13,
+}
+
+/// What a tile sounds like when it's hit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileSound {
+    Soft,
+    Crunch,
+    Hard,
+    Splash,
+}
+
+/// The sheets tiles are drawn from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sheet {
+    Tiles = 0,
+    Tiles2 = 1,
+}
+
+impl Sheet {
+    pub fn file(&self) -> &'static str {
+        match self {
+            Sheet::Tiles => "tiles.png",
+            Sheet::Tiles2 => "tiles2.png",
+        }
+    }
+}
+
+/// How a kind of tile looks and behaves
+pub struct TileProps {
+    /// Sheet its plain tile is on
+    pub sheet: Sheet,
+    /// Index of its plain tile in that sheet
+    pub texture: u32,
+    pub walkable: bool,
+    /// The player can swim through it slowly, mobs can't get in
+    pub swimmable: bool,
+    /// Hits it takes to break, None if it can't be broken
+    pub hardness: Option<u32>,
+    /// Tool that breaks it faster
+    pub tool: Option<ToolKind>,
+    /// Items left behind when it breaks, (item, most dropped)
+    pub drops: &'static [(&'static str, u32)],
+    /// What is left where it broke
+    pub becomes: TileType,
+    pub sound: TileSound,
+}
+
+// Plain ground that can be walked on and not broken
+const fn ground(sheet: Sheet, texture: u32, sound: TileSound) -> TileProps {
+    TileProps {
+        sheet,
+        texture,
+        walkable: true,
+        swimmable: false,
+        hardness: None,
+        tool: None,
+        drops: &[],
+        becomes: TileType::Dirt,
+        sound,
+    }
+}
+
+// In the same order as TileType, every tile is at its own number
+static REGISTRY: [(TileType, TileProps); 14] = [
+    (TileType::Grass, ground(Sheet::Tiles, 0, TileSound::Soft)),
+    (
+        TileType::Stone,
+        TileProps {
+            walkable: false,
+            hardness: Some(12),
+            tool: Some(ToolKind::Pickaxe),
+            drops: &[("pebble", 2)],
+            ..ground(Sheet::Tiles, 1, TileSound::Hard)
+        },
+    ),
+    (TileType::Sand, ground(Sheet::Tiles, 2, TileSound::Crunch)),
+    (TileType::Dirt, ground(Sheet::Tiles, 3, TileSound::Crunch)),
+    (
+        TileType::Water,
+        TileProps {
+            walkable: false,
+            swimmable: true,
+            ..ground(Sheet::Tiles, 4, TileSound::Splash)
+        },
+    ),
+    (TileType::Snow, ground(Sheet::Tiles2, 0, TileSound::Crunch)),
+    (TileType::Ice, ground(Sheet::Tiles2, 1, TileSound::Hard)),
+    (TileType::Mud, ground(Sheet::Tiles2, 2, TileSound::Soft)),
+    (
+        TileType::DarkGrass,
+        ground(Sheet::Tiles2, 3, TileSound::Soft),
+    ),
+    (
+        TileType::Lava,
+        TileProps {
+            walkable: false,
+            ..ground(Sheet::Tiles2, 4, TileSound::Splash)
+        },
+    ),
+    (
+        TileType::StairsDown,
+        ground(Sheet::Tiles2, 5, TileSound::Hard),
+    ),
+    (
+        TileType::StairsUp,
+        ground(Sheet::Tiles2, 6, TileSound::Hard),
+    ),
+    (TileType::Cloud, ground(Sheet::Tiles2, 7, TileSound::Soft)),
+    (
+        TileType::Sky,
+        TileProps {
+            walkable: false,
+            ..ground(Sheet::Tiles2, 8, TileSound::Soft)
+        },
+    ),
+];
+
+impl TileType {
+    pub fn props(&self) -> &'static TileProps {
+        &REGISTRY[*self as usize].1
+    }
+
+    /// The type of the tile at a position in a chunk's tilemap, None if there's no tile there
+    pub fn at(storage: &TileStorage, types: &Query<&TileType>, pos: &TilePos) -> Option<Self> {
+        types.get(storage.get(pos)?).ok().copied()
+    }
+
+    pub fn all() -> impl Iterator<Item = TileType> {
+        REGISTRY.iter().map(|(tile, _)| *tile)
+    }
+
+    pub fn is_walkable(&self) -> bool {
+        self.props().walkable
+    }
+}
+
+impl From<TileType> for u32 {
+    fn from(tile: TileType) -> Self {
+        tile as u32
+    }
+}
+
+impl TryFrom<u32> for TileType {
+    type Error = String;
+
+    fn try_from(id: u32) -> Result<Self, Self::Error> {
+        REGISTRY
+            .get(id as usize)
+            .map(|(tile, _)| *tile)
+            .ok_or_else(|| format!("no tile type {}", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_is_in_tile_type_order() {
+        for (i, (tile, _)) in REGISTRY.iter().enumerate() {
+            assert_eq!(*tile as usize, i, "{:?} is in the wrong place", tile);
+            assert_eq!(TileType::try_from(i as u32), Ok(*tile));
+        }
+        assert!(TileType::try_from(REGISTRY.len() as u32).is_err());
+    }
+}